@@ -0,0 +1,15 @@
+CREATE TABLE mod_versions (
+  id              UUID PRIMARY KEY NOT NULL,
+  mod_id          UUID NOT NULL,
+  version_number  TEXT NOT NULL,
+  description     TEXT NOT NULL,
+  downloads       BIGINT NOT NULL,
+  file_size       BIGINT NOT NULL,
+  download_url    TEXT NOT NULL,
+  date_created    TIMESTAMP WITH TIME ZONE NOT NULL,
+  is_active       BOOLEAN NOT NULL,
+
+  FOREIGN KEY(mod_id) REFERENCES mods(id)
+);
+
+CREATE INDEX mod_versions_mod_id_idx ON mod_versions(mod_id);
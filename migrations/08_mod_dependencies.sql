@@ -0,0 +1,10 @@
+CREATE TABLE mod_dependencies (
+  mod_id                UUID NOT NULL,
+  dependency_full_name  TEXT NOT NULL,
+  dependency_version    TEXT NOT NULL,
+
+  PRIMARY KEY(mod_id, dependency_full_name),
+  FOREIGN KEY(mod_id) REFERENCES mods(id)
+);
+
+CREATE INDEX mods_full_name_idx ON mods(full_name);
@@ -0,0 +1,10 @@
+ALTER TABLE mods ADD COLUMN fingerprint TEXT NOT NULL DEFAULT '';
+
+CREATE TABLE mod_import_summaries (
+  id         SERIAL PRIMARY KEY NOT NULL,
+  date       TIMESTAMP WITH TIME ZONE NOT NULL,
+  added      INTEGER NOT NULL,
+  updated    INTEGER NOT NULL,
+  unchanged  INTEGER NOT NULL,
+  failed     INTEGER NOT NULL
+);
@@ -0,0 +1,3 @@
+ALTER TABLE mods ADD COLUMN delisted_date TIMESTAMP WITH TIME ZONE;
+
+ALTER TABLE mod_import_summaries ADD COLUMN delisted INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,15 @@
+CREATE TYPE import_trigger AS ENUM ('Startup', 'Expiration', 'Manual');
+CREATE TYPE import_source AS ENUM ('Download', 'Cache');
+CREATE TYPE import_outcome AS ENUM ('Running', 'Success', 'Failure');
+
+CREATE TABLE import_runs (
+  id           SERIAL PRIMARY KEY NOT NULL,
+  started_at   TIMESTAMP WITH TIME ZONE NOT NULL,
+  finished_at  TIMESTAMP WITH TIME ZONE,
+  trigger      import_trigger NOT NULL,
+  source       import_source NOT NULL,
+  outcome      import_outcome NOT NULL,
+  error        TEXT
+);
+
+ALTER TABLE mod_import_summaries ADD COLUMN run_id INTEGER REFERENCES import_runs(id);
@@ -0,0 +1,8 @@
+ALTER TABLE mods ADD COLUMN community TEXT NOT NULL DEFAULT 'lethal-company';
+ALTER TABLE categories ADD COLUMN community TEXT NOT NULL DEFAULT 'lethal-company';
+
+-- communities can have categories with the same name
+ALTER TABLE categories DROP CONSTRAINT categories_name_key;
+ALTER TABLE categories ADD CONSTRAINT categories_community_name_key UNIQUE (community, name);
+
+CREATE INDEX mods_community_idx ON mods(community);
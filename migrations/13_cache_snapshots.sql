@@ -0,0 +1,2 @@
+ALTER TYPE import_trigger ADD VALUE 'Rollback';
+ALTER TYPE import_source ADD VALUE 'Snapshot';
@@ -0,0 +1,13 @@
+CREATE TYPE import_problem_kind AS ENUM ('NoVersions', 'UnknownCategory', 'InvalidVersion', 'InvalidDependency', 'InvalidId', 'InvalidDate');
+
+CREATE TABLE import_problems (
+  id         SERIAL PRIMARY KEY NOT NULL,
+  run_id     INTEGER NOT NULL REFERENCES import_runs(id),
+  community  TEXT NOT NULL,
+  mod_name   TEXT NOT NULL,
+  mod_id     TEXT NOT NULL,
+  kind       import_problem_kind NOT NULL,
+  reason     TEXT NOT NULL
+);
+
+CREATE INDEX import_problems_run_id_idx ON import_problems(run_id);
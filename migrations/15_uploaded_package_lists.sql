@@ -0,0 +1 @@
+ALTER TYPE import_source ADD VALUE 'Upload';
@@ -0,0 +1,29 @@
+ALTER TABLE mods ALTER updated_date TYPE TIMESTAMP WITH TIME ZONE;
+
+ALTER TABLE mods
+  ADD COLUMN date_created   TIMESTAMP WITH TIME ZONE,
+  ADD COLUMN donation_link  TEXT,
+  ADD COLUMN pinned         BOOLEAN NOT NULL DEFAULT false,
+  ADD COLUMN website_url    TEXT,
+  ADD COLUMN file_size      BIGINT NOT NULL DEFAULT 0,
+  ADD COLUMN downloads      BIGINT NOT NULL DEFAULT 0;
+
+-- best guesses from the stored versions, clearing the fingerprints makes the next import
+-- write the actual values
+UPDATE mods SET
+  date_created = COALESCE(
+    (SELECT MIN(date_created) FROM mod_versions WHERE mod_versions.mod_id = mods.id),
+    updated_date
+  ),
+  file_size = COALESCE(
+    (SELECT file_size FROM mod_versions WHERE mod_versions.mod_id = mods.id
+      ORDER BY date_created DESC LIMIT 1),
+    0
+  ),
+  downloads = COALESCE(
+    (SELECT SUM(downloads) FROM mod_versions WHERE mod_versions.mod_id = mods.id),
+    0
+  ),
+  fingerprint = '';
+
+ALTER TABLE mods ALTER date_created SET NOT NULL;
@@ -0,0 +1,5 @@
+-- icon_file is the file of the icon in the icon cache, cached_icon_url the icon_url it was
+-- fetched from. An icon that can't be cached has a cached_icon_url but no icon_file.
+ALTER TABLE mods
+  ADD COLUMN icon_file        TEXT,
+  ADD COLUMN cached_icon_url  TEXT;
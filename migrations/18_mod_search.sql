@@ -0,0 +1,11 @@
+-- Words of the name, owner and description for full-text search, weighted in that order.
+-- Names are also split at camel case so e.g. 'MoreSuits' is found by 'suits'. The 'simple'
+-- configuration is used since mod names aren't English words and shouldn't be stemmed.
+ALTER TABLE mods
+  ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', name || ' ' || regexp_replace(name, '([a-z0-9])([A-Z])', '\1 \2', 'g')), 'A') ||
+    setweight(to_tsvector('simple', owner), 'B') ||
+    setweight(to_tsvector('simple', description), 'C')
+  ) STORED;
+
+CREATE INDEX mods_search_vector ON mods USING GIN (search_vector);
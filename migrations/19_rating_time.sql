@@ -0,0 +1,5 @@
+-- When the mod was rated, unknown for the ratings made before this
+ALTER TABLE ratings ADD COLUMN rated_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE ratings ALTER COLUMN rated_at SET DEFAULT now();
+
+CREATE INDEX ratings_user_rated_at ON ratings (user_id, rated_at);
@@ -0,0 +1,16 @@
+-- Thunderstore lists some packages, e.g. BepInExPack, in more than one community with the same
+-- id. Each listing is a row of its own, with the categories, delisting and fingerprint of that
+-- community. Versions, dependencies and ratings belong to the package, so they refer to the id
+-- shared by its listings.
+ALTER TABLE mod_category DROP CONSTRAINT mod_category_mod_id_fkey;
+ALTER TABLE legacy_ratings DROP CONSTRAINT ratings_mod_id_fkey;
+ALTER TABLE ratings DROP CONSTRAINT ratings_mod_id_fkey1;
+ALTER TABLE mod_versions DROP CONSTRAINT mod_versions_mod_id_fkey;
+ALTER TABLE mod_dependencies DROP CONSTRAINT mod_dependencies_mod_id_fkey;
+
+ALTER TABLE mods DROP CONSTRAINT mods_pkey;
+ALTER TABLE mods ADD PRIMARY KEY (id, community);
+
+-- the fingerprints now include the community, clearing them makes the next import write every
+-- listing once
+UPDATE mods SET fingerprint = '';
@@ -1,14 +1,32 @@
-use std::{collections::HashSet, error::Error};
+use std::{
+	collections::{HashMap, HashSet},
+	error::Error,
+};
 
-use sqlx::{FromRow, Pool, Postgres, QueryBuilder, Row, postgres::PgPoolOptions};
-use time::{Date, OffsetDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+	FromRow, PgExecutor, Pool, Postgres, QueryBuilder, Row, Transaction, postgres::PgPoolOptions,
+};
+use time::OffsetDateTime;
 use uuid::Uuid;
 
 use crate::{
-	mods::{Category, Mod, Rating},
+	mods::{
+		Category, DEFAULT_COMMUNITY, Dependency, FullNameMatch, ImportOutcome, ImportPhase,
+		ImportProblem, ImportProblemKind, ImportProgress, ImportRun, ImportSource, ImportSummary,
+		ImportTrigger, Mod, PackageVersion, Page, RatedMod, Rating, RatingCounts, SearchResult,
+		UnresolvedDependency, Version,
+	},
 	services::users::{User, UserNoId},
 };
 
+/// common ratings added to the divisor of the similarity of users in the recommended order,
+/// so users who have rated only a couple of the same mods aren't considered very similar
+const SIMILARITY_SHRINK: f64 = 5.0;
+
+/// columns of `Mod`, for queries joining `categories` and grouping by mod
+const MOD_COLUMNS: &str = "mods.name, mods.owner, mods.description, mods.icon_url, mods.package_url, mods.id, mods.delisted_date IS NOT NULL delisted, array_remove(array_agg(categories.name), NULL) categories, mods.updated_date, mods.date_created, mods.donation_link, mods.pinned, mods.website_url, mods.file_size, mods.downloads, mods.icon_file";
+
 #[derive(Clone)]
 pub struct Database {
 	pool: Pool<Postgres>,
@@ -30,6 +48,11 @@ impl Database {
 		Ok(db)
 	}
 
+	#[cfg(test)]
+	pub fn from_pool(pool: Pool<Postgres>) -> Self {
+		Self { pool }
+	}
+
 	async fn apply_migrations(&self) -> Result<(), Box<dyn Error>> {
 		let migrator = sqlx::migrate!("./migrations");
 		migrator.run(&self.pool).await?;
@@ -37,20 +60,68 @@ impl Database {
 		Ok(())
 	}
 
+	/// Mods the user hasn't rated yet, in the order of `options.order`.
+	///
+	/// The recommended order is collaborative filtering over the ratings table. Users who rated
+	/// the same mods as the user get a similarity of (agreements - disagreements) / (common
+	/// ratings + `SIMILARITY_SHRINK`), so a few shared ratings count less than many. Each mod is
+	/// scored by the sum of the similarities of the users who liked it minus those who
+	/// disliked it, counting only users with a positive similarity. Mods without a score,
+	/// including every mod for a user with no ratings in common with anyone, fall back to the
+	/// most recently updated first.
 	pub async fn get_mods(
 		&self,
 		options: &ModQueryOptions,
 		user_id: i32,
 	) -> Result<Vec<Mod>, Box<dyn Error>> {
-		let mut builder = QueryBuilder::new("
-			SELECT mods.name, mods.owner, mods.description, mods.icon_url, mods.package_url, mods.id, array_remove(array_agg(categories.name), NULL) categories
+		let mut builder = QueryBuilder::new("");
+		let recommended = options.order == QueueOrder::Recommended;
+
+		if recommended {
+			builder.push(
+				"WITH neighbours AS (
+					SELECT others.user_id,
+						SUM(CASE WHEN others.rating = mine.rating THEN 1 ELSE -1 END)::FLOAT8
+							/ (COUNT(*) + ",
+			);
+			builder.push_bind(SIMILARITY_SHRINK);
+			builder.push(
+				") similarity
+					FROM ratings mine
+					JOIN ratings others ON others.mod_id = mine.mod_id AND others.user_id <> mine.user_id
+					WHERE mine.user_id = ",
+			);
+			builder.push_bind(user_id);
+			builder.push(
+				" GROUP BY others.user_id
+				),
+				scores AS (
+					SELECT ratings.mod_id,
+						SUM(neighbours.similarity * CASE WHEN ratings.rating = 'Like' THEN 1 ELSE -1 END) score
+					FROM neighbours
+					JOIN ratings ON ratings.user_id = neighbours.user_id
+					WHERE neighbours.similarity > 0
+					GROUP BY ratings.mod_id
+				) ",
+			);
+		}
+
+		builder.push(format!(
+			"SELECT {MOD_COLUMNS}
 			FROM mods
 			LEFT JOIN mod_category ON mod_category.mod_id = mods.id
-			LEFT JOIN categories ON categories.id = mod_category.category_id ",
-		);
+			LEFT JOIN categories ON categories.id = mod_category.category_id AND categories.community = mods.community "
+		));
+		if recommended {
+			builder.push("LEFT JOIN scores ON scores.mod_id = mods.id ");
+		}
 		builder.push("WHERE mods.id NOT IN (SELECT mod_id FROM ratings WHERE ratings.user_id =");
 		builder.push_bind(user_id);
 		builder.push(") ");
+		builder.push("AND mods.delisted_date IS NULL ");
+		builder.push("AND mods.community = ");
+		builder.push_bind(&options.community);
+		builder.push(" ");
 
 		if !options.include_deprecated {
 			builder.push("AND mods.deprecated = false ");
@@ -61,13 +132,15 @@ impl Database {
 		}
 
 		let ignored_categories = &options.ignored_categories;
-		if ignored_categories.len() != 0 {
+		if !ignored_categories.is_empty() {
 			builder.push(
 				"AND mods.id NOT IN
 					(SELECT mod_category.mod_id FROM mod_category
 					JOIN categories ON categories.id = mod_category.category_id
-					WHERE categories.name IN ",
+					WHERE categories.community = ",
 			);
+			builder.push_bind(&options.community);
+			builder.push(" AND categories.name IN ");
 
 			builder.push_tuples(ignored_categories, |mut b, category| {
 				b.push_bind(category);
@@ -76,12 +149,15 @@ impl Database {
 			builder.push(") ");
 		}
 
-		let query = builder
-			.push("GROUP BY mods.id ")
-			.push("ORDER BY mods.updated_date DESC ")
-			.push("LIMIT ")
-			.push_bind(options.limit)
-			.build();
+		if recommended {
+			builder.push("GROUP BY mods.id, mods.community, scores.score ");
+			builder.push("ORDER BY COALESCE(scores.score, 0) DESC, mods.updated_date DESC ");
+		} else {
+			builder.push("GROUP BY mods.id, mods.community ");
+			builder.push("ORDER BY mods.updated_date DESC ");
+		}
+
+		let query = builder.push("LIMIT ").push_bind(options.limit).build();
 
 		let mods = query
 			.fetch_all(&self.pool)
@@ -92,61 +168,158 @@ impl Database {
 		Ok(mods)
 	}
 
-	pub async fn insert_categories(
+	/// the mod if it is a mod of the community
+	pub async fn get_mod(
+		&self,
+		mod_id: &Uuid,
+		community: &str,
+	) -> Result<Option<Mod>, Box<dyn Error>> {
+		let sql = format!(
+			"SELECT {MOD_COLUMNS}
+			FROM mods
+			LEFT JOIN mod_category ON mod_category.mod_id = mods.id
+			LEFT JOIN categories ON categories.id = mod_category.category_id AND categories.community = mods.community
+			WHERE mods.id = $1
+			AND mods.community = $2
+			GROUP BY mods.id, mods.community;"
+		);
+
+		let modd = sqlx::query_as(&sql)
+			.bind(mod_id)
+			.bind(community)
+			.fetch_optional(&self.pool)
+			.await?;
+		Ok(modd)
+	}
+
+	pub async fn begin_import(
 		&self,
+		progress: ImportProgress,
+	) -> Result<ImportTransaction, Box<dyn Error>> {
+		let tx = self.pool.begin().await?;
+		Ok(ImportTransaction { tx, progress })
+	}
+
+	pub async fn get_categories(&self, community: &str) -> Result<Vec<Category>, Box<dyn Error>> {
+		query_categories(&self.pool, community).await
+	}
+}
+
+/// Writes of a mod import. Nothing is visible to readers until the import is committed, so they
+/// never see a partially imported package list.
+pub struct ImportTransaction {
+	tx: Transaction<'static, Postgres>,
+	progress: ImportProgress,
+}
+
+impl ImportTransaction {
+	pub async fn insert_categories(
+		&mut self,
+		community: &str,
 		categories: &HashSet<impl ToString>,
 	) -> Result<(), Box<dyn Error>> {
-		if categories.len() == 0 {
+		if categories.is_empty() {
 			return Ok(());
 		}
 
+		self.progress.phase(ImportPhase::InsertingCategories);
 		let categories = categories.iter().map(|s| s.to_string()).collect::<Vec<_>>();
 
-		let mut builder = QueryBuilder::new("INSERT INTO categories(name)");
+		let mut builder = QueryBuilder::new("INSERT INTO categories(community, name)");
 		builder
 			.push_values(&categories, |mut b, category| {
-				b.push_bind(category);
+				b.push_bind(community).push_bind(category);
 			})
 			.push("ON CONFLICT DO NOTHING;");
 
 		let query = builder.build();
-		query.execute(&self.pool).await?;
+		query.execute(&mut *self.tx).await?;
 
 		Ok(())
 	}
 
-	pub async fn get_categories(&self) -> Result<Vec<Category>, Box<dyn Error>> {
-		let categories = sqlx::query_as("SELECT id, name FROM categories;")
-			.fetch_all(&self.pool)
-			.await?;
-		Ok(categories)
+	/// categories of the community, including ones inserted in this transaction
+	pub async fn get_categories(
+		&mut self,
+		community: &str,
+	) -> Result<Vec<Category>, Box<dyn Error>> {
+		query_categories(&mut *self.tx, community).await
 	}
 
+	/// Insert or update the listings of the mods in the community, along with the versions,
+	/// dependencies and categories of the mods
 	pub async fn insert_mods(
-		&self,
+		&mut self,
+		community: &str,
 		mods: &Vec<InsertMod<'_>>,
 		chunk_size: usize,
 	) -> Result<(), Box<dyn Error>> {
-		self.clear_categories_junction_table().await?;
+		let mod_ids = mods.iter().map(|m| m.uuid4).collect::<Vec<_>>();
+		self.clear_mod_relations(community, &mod_ids).await?;
 
 		let mod_chunks = mods.chunks(chunk_size);
 		let mod_chunks_count = mod_chunks.len();
 
 		for (index, chunk) in mod_chunks.enumerate() {
 			log::debug!("Inserting mods chunk {}/{}", index + 1, mod_chunks_count);
+			self.progress
+				.chunk(ImportPhase::InsertingMods, index + 1, mod_chunks_count);
 
 			self.insert_mods_data(&chunk.iter().collect()).await?;
 		}
 
+		let versions = mods
+			.iter()
+			.flat_map(|m| m.versions.iter())
+			.collect::<Vec<_>>();
+
+		let version_chunks = versions.chunks(chunk_size);
+		let version_chunks_count = version_chunks.len();
+		for (index, chunk) in version_chunks.enumerate() {
+			log::debug!(
+				"Inserting mod versions chunk {}/{}",
+				index + 1,
+				version_chunks_count
+			);
+			self.progress.chunk(
+				ImportPhase::InsertingVersions,
+				index + 1,
+				version_chunks_count,
+			);
+
+			self.insert_mod_versions_data(chunk).await?;
+		}
+
+		let dependencies = mods
+			.iter()
+			.flat_map(|m| m.dependencies.iter())
+			.collect::<Vec<_>>();
+
+		let dependency_chunks = dependencies.chunks(chunk_size);
+		let dependency_chunks_count = dependency_chunks.len();
+		for (index, chunk) in dependency_chunks.enumerate() {
+			log::debug!(
+				"Inserting mod dependencies chunk {}/{}",
+				index + 1,
+				dependency_chunks_count
+			);
+			self.progress.chunk(
+				ImportPhase::InsertingDependencies,
+				index + 1,
+				dependency_chunks_count,
+			);
+
+			self.insert_mod_dependencies_data(chunk).await?;
+		}
+
 		let mod_categories = mods
 			.iter()
-			.map(|m| {
+			.flat_map(|m| {
 				m.category_ids.iter().map(|c_id| InsertModCategory {
 					mod_id: &m.uuid4,
-					category_id: &c_id,
+					category_id: c_id,
 				})
 			})
-			.flatten()
 			.collect::<Vec<_>>();
 
 		let category_chunks = mod_categories.chunks(chunk_size);
@@ -157,6 +330,11 @@ impl Database {
 				index + 1,
 				category_chunks_count
 			);
+			self.progress.chunk(
+				ImportPhase::InsertingCategoryJunctions,
+				index + 1,
+				category_chunks_count,
+			);
 
 			self.insert_mod_category_junction_data(&chunk.iter().collect())
 				.await?;
@@ -165,13 +343,13 @@ impl Database {
 		Ok(())
 	}
 
-	async fn insert_mods_data(&self, mods: &Vec<&InsertMod<'_>>) -> Result<(), Box<dyn Error>> {
-		if mods.len() == 0 {
+	async fn insert_mods_data(&mut self, mods: &Vec<&InsertMod<'_>>) -> Result<(), Box<dyn Error>> {
+		if mods.is_empty() {
 			return Ok(());
 		}
 
 		let mut builder = QueryBuilder::new(
-			"INSERT INTO mods (id, name, description, icon_url, full_name, owner, package_url, updated_date, rating, deprecated, nsfw) ",
+			"INSERT INTO mods (id, name, description, icon_url, full_name, owner, package_url, updated_date, date_created, donation_link, pinned, website_url, file_size, downloads, rating, deprecated, nsfw, fingerprint, community) ",
 		);
 
 		builder.push_values(mods, |mut b, m| {
@@ -183,13 +361,21 @@ impl Database {
 			b.push_bind(m.owner);
 			b.push_bind(m.package_url);
 			b.push_bind(m.updated_date);
+			b.push_bind(m.date_created);
+			b.push_bind(m.donation_link);
+			b.push_bind(m.is_pinned);
+			b.push_bind(m.website_url);
+			b.push_bind(m.file_size);
+			b.push_bind(m.downloads);
 			b.push_bind(m.rating);
 			b.push_bind(m.is_deprecated);
 			b.push_bind(m.has_nsfw_content);
+			b.push_bind(&m.fingerprint);
+			b.push_bind(m.community);
 		});
 
 		builder.push(
-			" ON CONFLICT(id) DO UPDATE SET
+			" ON CONFLICT(id, community) DO UPDATE SET
 name        =EXCLUDED.name,
 description =EXCLUDED.description,
 icon_url    =EXCLUDED.icon_url,
@@ -197,21 +383,91 @@ full_name   =EXCLUDED.full_name,
 owner       =EXCLUDED.owner,
 package_url =EXCLUDED.package_url,
 updated_date=EXCLUDED.updated_date,
+date_created=EXCLUDED.date_created,
+donation_link=EXCLUDED.donation_link,
+pinned      =EXCLUDED.pinned,
+website_url =EXCLUDED.website_url,
+file_size   =EXCLUDED.file_size,
+downloads   =EXCLUDED.downloads,
 rating      =EXCLUDED.rating,
 deprecated  =EXCLUDED.deprecated,
-nsfw        =EXCLUDED.nsfw",
+nsfw        =EXCLUDED.nsfw,
+fingerprint =EXCLUDED.fingerprint",
+		);
+
+		let query = builder.build();
+		query.execute(&mut *self.tx).await?;
+		Ok(())
+	}
+
+	async fn insert_mod_versions_data(
+		&mut self,
+		versions: &[&InsertModVersion<'_>],
+	) -> Result<(), Box<dyn Error>> {
+		if versions.is_empty() {
+			return Ok(());
+		}
+
+		let mut builder = QueryBuilder::new(
+			"INSERT INTO mod_versions (id, mod_id, version_number, description, downloads, file_size, download_url, date_created, is_active) ",
+		);
+
+		builder.push_values(versions, |mut b, v| {
+			b.push_bind(v.uuid4);
+			b.push_bind(v.mod_id);
+			b.push_bind(v.version_number);
+			b.push_bind(v.description);
+			b.push_bind(v.downloads);
+			b.push_bind(v.file_size);
+			b.push_bind(v.download_url);
+			b.push_bind(v.date_created);
+			b.push_bind(v.is_active);
+		});
+
+		builder.push(
+			" ON CONFLICT(id) DO UPDATE SET
+version_number=EXCLUDED.version_number,
+description   =EXCLUDED.description,
+downloads     =EXCLUDED.downloads,
+file_size     =EXCLUDED.file_size,
+download_url  =EXCLUDED.download_url,
+date_created  =EXCLUDED.date_created,
+is_active     =EXCLUDED.is_active",
+		);
+
+		let query = builder.build();
+		query.execute(&mut *self.tx).await?;
+		Ok(())
+	}
+
+	async fn insert_mod_dependencies_data(
+		&mut self,
+		dependencies: &[&InsertModDependency<'_>],
+	) -> Result<(), Box<dyn Error>> {
+		if dependencies.is_empty() {
+			return Ok(());
+		}
+
+		let mut builder = QueryBuilder::new(
+			"INSERT INTO mod_dependencies (mod_id, dependency_full_name, dependency_version) ",
 		);
+		builder.push_values(dependencies, |mut b, dependency| {
+			b.push_bind(dependency.mod_id)
+				.push_bind(dependency.full_name)
+				.push_bind(dependency.version);
+		});
+		builder.push("ON CONFLICT DO NOTHING;");
 
 		let query = builder.build();
-		query.execute(&self.pool).await?;
+		query.execute(&mut *self.tx).await?;
 		Ok(())
 	}
 
 	async fn insert_mod_category_junction_data(
-		&self,
+		&mut self,
 		mod_categories: &Vec<&InsertModCategory<'_>>,
 	) -> Result<(), Box<dyn Error>> {
-		if mod_categories.len() == 0 {
+		if mod_categories.is_empty() {
 			return Ok(());
 		}
 
@@ -223,17 +479,294 @@ nsfw        =EXCLUDED.nsfw",
 		builder.push("ON CONFLICT DO NOTHING;");
 
 		let query = builder.build();
-		query.execute(&self.pool).await?;
+		query.execute(&mut *self.tx).await?;
+		Ok(())
+	}
+
+	/// Remove categories and dependencies of the mods, they are inserted again along with the mods.
+	/// Only the categories of the community are removed, the listings of the mods in other
+	/// communities keep theirs.
+	async fn clear_mod_relations(
+		&mut self,
+		community: &str,
+		mod_ids: &[Uuid],
+	) -> Result<(), Box<dyn Error>> {
+		sqlx::query(
+			"DELETE FROM mod_category
+			WHERE mod_id = ANY($1)
+			AND category_id IN (SELECT id FROM categories WHERE community = $2);",
+		)
+		.bind(mod_ids)
+		.bind(community)
+		.execute(&mut *self.tx)
+		.await?;
+
+		sqlx::query("DELETE FROM mod_dependencies WHERE mod_id = ANY($1);")
+			.bind(mod_ids)
+			.execute(&mut *self.tx)
+			.await?;
+
+		Ok(())
+	}
+
+	/// Mark mods of the community missing from `listed_mod_ids` as delisted, and clear the mark
+	/// from listed ones in case they have returned. Returns how many mods were newly delisted.
+	pub async fn update_delisted_mods(
+		&mut self,
+		community: &str,
+		listed_mod_ids: &[Uuid],
+		date: OffsetDateTime,
+	) -> Result<u64, Box<dyn Error>> {
+		self.progress.phase(ImportPhase::Delisting);
+
+		sqlx::query(
+			"UPDATE mods SET delisted_date = NULL WHERE delisted_date IS NOT NULL AND community = $2 AND id = ANY($1);",
+		)
+		.bind(listed_mod_ids)
+		.bind(community)
+		.execute(&mut *self.tx)
+		.await?;
+
+		let result = sqlx::query(
+			"UPDATE mods SET delisted_date = $2 WHERE delisted_date IS NULL AND community = $3 AND NOT (id = ANY($1));",
+		)
+		.bind(listed_mod_ids)
+		.bind(date)
+		.bind(community)
+		.execute(&mut *self.tx)
+		.await?;
+
+		Ok(result.rows_affected())
+	}
+
+	/// Refresh the download counts and ratings of mods that are otherwise unchanged, see
+	/// `ModRaw::fingerprint`
+	pub async fn update_mod_counters(
+		&mut self,
+		community: &str,
+		mods: &[InsertMod<'_>],
+	) -> Result<(), Box<dyn Error>> {
+		let mod_ids = mods.iter().map(|m| m.uuid4).collect::<Vec<_>>();
+		let downloads = mods.iter().map(|m| m.downloads).collect::<Vec<_>>();
+		let ratings = mods.iter().map(|m| m.rating).collect::<Vec<_>>();
+
+		sqlx::query(
+			"UPDATE mods SET downloads = c.downloads, rating = c.rating
+FROM UNNEST($1::uuid[], $2::bigint[], $3::bigint[]) AS c(id, downloads, rating)
+WHERE mods.id = c.id AND mods.community = $4
+AND (mods.downloads <> c.downloads OR mods.rating <> c.rating);",
+		)
+		.bind(&mod_ids)
+		.bind(&downloads)
+		.bind(&ratings)
+		.bind(community)
+		.execute(&mut *self.tx)
+		.await?;
+
+		let versions = mods.iter().flat_map(|m| m.versions.iter());
+		let (version_ids, version_downloads): (Vec<_>, Vec<_>) =
+			versions.map(|v| (v.uuid4, v.downloads)).unzip();
+
+		sqlx::query(
+			"UPDATE mod_versions SET downloads = c.downloads
+FROM UNNEST($1::uuid[], $2::bigint[]) AS c(id, downloads)
+WHERE mod_versions.id = c.id AND mod_versions.downloads <> c.downloads;",
+		)
+		.bind(&version_ids)
+		.bind(&version_downloads)
+		.execute(&mut *self.tx)
+		.await?;
+
+		Ok(())
+	}
+
+	/// fingerprints of the stored listings of the mods in the community, see `ModRaw::fingerprint`
+	pub async fn get_mod_fingerprints(
+		&mut self,
+		community: &str,
+		mod_ids: &[Uuid],
+	) -> Result<HashMap<Uuid, String>, Box<dyn Error>> {
+		let fingerprints = sqlx::query_as::<_, (Uuid, String)>(
+			"SELECT id, fingerprint FROM mods WHERE id = ANY($1) AND community = $2;",
+		)
+		.bind(mod_ids)
+		.bind(community)
+		.fetch_all(&mut *self.tx)
+		.await?
+		.into_iter()
+		.collect();
+
+		Ok(fingerprints)
+	}
+
+	pub async fn commit(self) -> Result<(), Box<dyn Error>> {
+		self.progress.phase(ImportPhase::Committing);
+		self.tx.commit().await?;
 		Ok(())
 	}
+}
+
+async fn query_categories<'e>(
+	executor: impl PgExecutor<'e>,
+	community: &str,
+) -> Result<Vec<Category>, Box<dyn Error>> {
+	let categories = sqlx::query_as("SELECT id, name FROM categories WHERE community = $1;")
+		.bind(community)
+		.fetch_all(executor)
+		.await?;
+	Ok(categories)
+}
+
+impl Database {
+	/// versions of the mod, most recently created first
+	pub async fn get_mod_versions(&self, mod_id: &Uuid) -> Result<Vec<Version>, Box<dyn Error>> {
+		let versions = sqlx::query_as(
+			"SELECT version_number, description, downloads, file_size, download_url, date_created, is_active
+			FROM mod_versions
+			WHERE mod_id = $1
+			ORDER BY date_created DESC;",
+		)
+		.bind(mod_id)
+		.fetch_all(&self.pool)
+		.await?;
+
+		Ok(versions)
+	}
+
+	/// full name and most recent version of each mod, mods without any versions are omitted
+	pub async fn get_latest_versions(
+		&self,
+		mod_ids: &[Uuid],
+	) -> Result<Vec<PackageVersion>, Box<dyn Error>> {
+		let sql = "SELECT DISTINCT ON (mods.full_name) mods.full_name, mod_versions.version_number
+			FROM mods
+			JOIN mod_versions ON mod_versions.mod_id = mods.id
+			WHERE mods.id = ANY($1)
+			ORDER BY mods.full_name, mod_versions.date_created DESC;";
+
+		let versions = sqlx::query_as(sql)
+			.bind(mod_ids)
+			.fetch_all(&self.pool)
+			.await?;
+
+		Ok(versions)
+	}
+
+	/// transitive dependencies of a mod, see `get_dependencies_of_mods`
+	pub async fn get_dependencies(
+		&self,
+		mod_id: &Uuid,
+		community: &str,
+	) -> Result<Vec<Dependency>, Box<dyn Error>> {
+		self.get_dependencies_of_mods(&[*mod_id], community).await
+	}
 
-	async fn clear_categories_junction_table(&self) -> Result<(), Box<dyn Error>> {
-		sqlx::query("DELETE FROM mod_category;")
+	/// Transitive dependencies of all given mods of the community, each dependency is listed only
+	/// once. Dependencies are looked up from the same community.
+	/// Dependencies that can't be found from the mods table are included with `mod_id` set to None,
+	/// their own dependencies are unknown.
+	pub async fn get_dependencies_of_mods(
+		&self,
+		mod_ids: &[Uuid],
+		community: &str,
+	) -> Result<Vec<Dependency>, Box<dyn Error>> {
+		if mod_ids.is_empty() {
+			return Ok(Vec::new());
+		}
+
+		let sql = "WITH RECURSIVE deps(full_name, community) AS (
+				SELECT mod_dependencies.dependency_full_name, mods.community
+				FROM mod_dependencies
+				JOIN mods ON mods.id = mod_dependencies.mod_id AND mods.community = $2
+				WHERE mod_dependencies.mod_id = ANY($1)
+				UNION
+				SELECT mod_dependencies.dependency_full_name, mods.community
+				FROM deps
+				JOIN mods ON mods.full_name = deps.full_name AND mods.community = deps.community
+				JOIN mod_dependencies ON mod_dependencies.mod_id = mods.id
+			)
+			SELECT deps.full_name, mods.id mod_id, mods.package_url
+			FROM deps
+			LEFT JOIN mods ON mods.full_name = deps.full_name AND mods.community = deps.community
+			ORDER BY deps.full_name;";
+
+		let dependencies = sqlx::query_as(sql)
+			.bind(mod_ids)
+			.bind(community)
+			.fetch_all(&self.pool)
+			.await?;
+
+		Ok(dependencies)
+	}
+
+	/// dependencies which don't match any imported mod
+	pub async fn get_unresolved_dependencies(
+		&self,
+	) -> Result<Vec<UnresolvedDependency>, Box<dyn Error>> {
+		let sql = "SELECT mods.full_name mod_full_name, mod_dependencies.dependency_full_name, mod_dependencies.dependency_version
+			FROM mod_dependencies
+			JOIN mods ON mods.id = mod_dependencies.mod_id
+			WHERE NOT EXISTS (
+				SELECT 1 FROM mods dependency
+				WHERE dependency.full_name = mod_dependencies.dependency_full_name
+				AND dependency.community = mods.community
+			)
+			ORDER BY mods.full_name, mod_dependencies.dependency_full_name;";
+
+		let dependencies = sqlx::query_as(sql).fetch_all(&self.pool).await?;
+		Ok(dependencies)
+	}
+
+	/// Listed mods whose icon hasn't been fetched since their icon url last changed. Returns the
+	/// mod ids with the icon urls.
+	pub async fn get_mods_without_cached_icon(
+		&self,
+	) -> Result<Vec<(Uuid, String)>, Box<dyn Error>> {
+		let sql = "SELECT id, icon_url FROM mods
+			WHERE icon_url <> ''
+			AND delisted_date IS NULL
+			AND cached_icon_url IS DISTINCT FROM icon_url
+			GROUP BY id, icon_url
+			ORDER BY MAX(updated_date) DESC;";
+
+		let mods = sqlx::query_as(sql).fetch_all(&self.pool).await?;
+		Ok(mods)
+	}
+
+	/// `icon_file` is None if the icon at `icon_url` can't be cached
+	pub async fn set_cached_icon(
+		&self,
+		mod_id: &Uuid,
+		icon_url: &str,
+		icon_file: Option<&str>,
+	) -> Result<(), Box<dyn Error>> {
+		sqlx::query("UPDATE mods SET icon_file = $2, cached_icon_url = $3 WHERE id = $1;")
+			.bind(mod_id)
+			.bind(icon_file)
+			.bind(icon_url)
 			.execute(&self.pool)
 			.await?;
 		Ok(())
 	}
 
+	pub async fn get_icon_file(&self, mod_id: &Uuid) -> Result<Option<String>, Box<dyn Error>> {
+		let icon_file = sqlx::query_scalar("SELECT icon_file FROM mods WHERE id = $1 LIMIT 1;")
+			.bind(mod_id)
+			.fetch_optional(&self.pool)
+			.await?
+			.flatten();
+		Ok(icon_file)
+	}
+
+	/// icon files used by any mod
+	pub async fn get_icon_files(&self) -> Result<HashSet<String>, Box<dyn Error>> {
+		let files: Vec<String> =
+			sqlx::query_scalar("SELECT DISTINCT icon_file FROM mods WHERE icon_file IS NOT NULL;")
+				.fetch_all(&self.pool)
+				.await?;
+		Ok(files.into_iter().collect())
+	}
+
 	pub async fn latest_mod_import_date(&self) -> Result<Option<OffsetDateTime>, Box<dyn Error>> {
 		let result = sqlx::query("SELECT date FROM mods_imported_date WHERE id = 0;")
 			.fetch_optional(&self.pool)
@@ -257,503 +790,2103 @@ nsfw        =EXCLUDED.nsfw",
 		Ok(())
 	}
 
-	pub async fn insert_mod_rating(
+	pub async fn start_import_run(
 		&self,
-		mod_id: &Uuid,
-		rating: &Rating,
-		user_id: i32,
-	) -> Result<(), Box<dyn Error>> {
-		sqlx::query("INSERT INTO ratings(mod_id, rating, user_id) VALUES ($1, $2, $3);")
-			.bind(mod_id)
-			.bind(rating)
-			.bind(user_id)
-			.execute(&self.pool)
-			.await?;
-		Ok(())
+		trigger: ImportTrigger,
+		source: ImportSource,
+	) -> Result<i32, Box<dyn Error>> {
+		let (id,) = sqlx::query_as(
+			"INSERT INTO import_runs (started_at, trigger, source, outcome) VALUES ($1, $2, $3, $4) RETURNING id;",
+		)
+		.bind(OffsetDateTime::now_utc())
+		.bind(trigger)
+		.bind(source)
+		.bind(ImportOutcome::Running)
+		.fetch_one(&self.pool)
+		.await?;
+
+		Ok(id)
 	}
 
-	pub async fn get_rated_mods(
+	/// mark the run as finished, the run failed if there's an error message
+	pub async fn finish_import_run(
 		&self,
-		rating: &Rating,
-		limit: i16,
-		user_id: i32,
-	) -> Result<Vec<Mod>, Box<dyn Error>> {
-		let sql = "SELECT mods.name, mods.owner, mods.description, mods.icon_url, mods.package_url, mods.id, array_remove(array_agg(categories.name), NULL) categories
-			FROM mods
-			JOIN ratings ON mods.id = ratings.mod_id
-			LEFT JOIN mod_category ON mod_category.mod_id = mods.id
-			LEFT JOIN categories ON categories.id = mod_category.category_id
-			WHERE ratings.rating = $1
-			AND ratings.user_id = $2
-			GROUP BY mods.id
-			LIMIT $3;";
+		run_id: i32,
+		error: Option<&str>,
+	) -> Result<(), Box<dyn Error>> {
+		let outcome = match error {
+			Some(_) => ImportOutcome::Failure,
+			None => ImportOutcome::Success,
+		};
 
-		let mods = sqlx::query_as(sql)
-			.bind(rating)
-			.bind(user_id)
-			.bind(limit)
-			.fetch_all(&self.pool)
-			.await?;
+		sqlx::query(
+			"UPDATE import_runs SET finished_at = $2, outcome = $3, error = $4 WHERE id = $1;",
+		)
+		.bind(run_id)
+		.bind(OffsetDateTime::now_utc())
+		.bind(outcome)
+		.bind(error)
+		.execute(&self.pool)
+		.await?;
 
-		Ok(mods)
+		Ok(())
 	}
 
-	/// return the created user on success, return None if username was already taken
-	pub async fn insert_user(&self, user: &UserNoId) -> Result<Option<User>, Box<dyn Error>> {
-		let result = sqlx::query_as(
-			"INSERT INTO users(username, password_hash) VALUES ($1, $2) ON CONFLICT DO NOTHING RETURNING id, username, password_hash, has_import_privileges;",
+	/// Runs still marked as running can't be running anymore after a restart, mark them as failed
+	pub async fn fail_interrupted_import_runs(&self) -> Result<(), Box<dyn Error>> {
+		sqlx::query(
+			"UPDATE import_runs SET finished_at = $1, outcome = $2, error = 'Interrupted by server shutdown' WHERE outcome = $3;",
 		)
-		.bind(&user.username)
-		.bind(&user.password_hash)
-		.fetch_optional(&self.pool).await?;
+		.bind(OffsetDateTime::now_utc())
+		.bind(ImportOutcome::Failure)
+		.bind(ImportOutcome::Running)
+		.execute(&self.pool)
+		.await?;
 
-		Ok(result)
+		Ok(())
 	}
 
-	pub async fn find_user(&self, name: &str) -> Result<Option<User>, Box<dyn Error>> {
-		let result = sqlx::query_as(
-			"SELECT id, username, password_hash, has_import_privileges FROM users WHERE username = $1;",
-		)
-		.bind(name)
-		.fetch_optional(&self.pool)
-		.await?;
+	/// when the latest failed import run was started
+	pub async fn latest_failed_import_date(
+		&self,
+	) -> Result<Option<OffsetDateTime>, Box<dyn Error>> {
+		let date =
+			sqlx::query_scalar("SELECT MAX(started_at) FROM import_runs WHERE outcome = $1;")
+				.bind(ImportOutcome::Failure)
+				.fetch_one(&self.pool)
+				.await?;
 
-		Ok(result)
+		Ok(date)
 	}
 
-	pub async fn find_user_by_id(&self, id: i32) -> Result<Option<User>, Box<dyn Error>> {
-		let result = sqlx::query_as(
-			"SELECT id, username, password_hash, has_import_privileges FROM users WHERE id = $1;",
+	/// most recent import runs first
+	pub async fn get_import_runs(&self, limit: i32) -> Result<Vec<ImportRun>, Box<dyn Error>> {
+		let runs = sqlx::query_as(
+			"SELECT import_runs.id, import_runs.started_at, import_runs.finished_at, import_runs.trigger, import_runs.source, import_runs.outcome, import_runs.error,
+				mod_import_summaries.added, mod_import_summaries.updated, mod_import_summaries.unchanged, mod_import_summaries.failed, mod_import_summaries.delisted,
+				(SELECT COUNT(*) FROM import_problems WHERE import_problems.run_id = import_runs.id) AS problems
+			FROM import_runs
+			LEFT JOIN mod_import_summaries ON mod_import_summaries.run_id = import_runs.id
+			ORDER BY import_runs.started_at DESC, import_runs.id DESC
+			LIMIT $1;",
 		)
-		.bind(id)
-		.fetch_optional(&self.pool)
+		.bind(limit)
+		.fetch_all(&self.pool)
 		.await?;
 
-		Ok(result)
+		Ok(runs)
 	}
-}
 
-pub struct InsertMod<'a> {
-	pub uuid4: Uuid,
-	pub name: &'a String,
-	pub description: &'a str,
-	pub icon_url: &'a str,
-	pub full_name: &'a String,
-	pub owner: &'a String,
-	pub package_url: &'a String,
-	pub updated_date: Date,
-	pub rating: i64,
-	pub is_deprecated: bool,
-	pub has_nsfw_content: bool,
-	pub category_ids: HashSet<&'a i32>,
+	pub async fn insert_import_problems(
+		&self,
+		run_id: i32,
+		problems: &[ImportProblem],
+		chunk_size: usize,
+	) -> Result<(), Box<dyn Error>> {
+		for chunk in problems.chunks(chunk_size) {
+			let mut builder = QueryBuilder::new(
+				"INSERT INTO import_problems(run_id, community, mod_name, mod_id, kind, reason)",
+			);
+			builder.push_values(chunk, |mut b, problem| {
+				b.push_bind(run_id)
+					.push_bind(&problem.community)
+					.push_bind(&problem.mod_name)
+					.push_bind(&problem.mod_id)
+					.push_bind(problem.kind)
+					.push_bind(&problem.reason);
+			});
+
+			builder.build().execute(&self.pool).await?;
+		}
+
+		Ok(())
+	}
+
+	/// problems found during an import run, in the order they were found
+	pub async fn get_import_problems(
+		&self,
+		run_id: i32,
+		limit: i32,
+	) -> Result<Vec<ImportProblem>, Box<dyn Error>> {
+		let problems = sqlx::query_as(
+			"SELECT community, mod_name, mod_id, kind, reason FROM import_problems WHERE run_id = $1 ORDER BY id LIMIT $2;",
+		)
+		.bind(run_id)
+		.bind(limit)
+		.fetch_all(&self.pool)
+		.await?;
+
+		Ok(problems)
+	}
+
+	/// how many problems of each kind were found during an import run, most common first
+	pub async fn count_import_problems(
+		&self,
+		run_id: i32,
+	) -> Result<Vec<(ImportProblemKind, i64)>, Box<dyn Error>> {
+		let counts = sqlx::query_as(
+			"SELECT kind, COUNT(*) AS count FROM import_problems WHERE run_id = $1 GROUP BY kind ORDER BY count DESC, kind;",
+		)
+		.bind(run_id)
+		.fetch_all(&self.pool)
+		.await?;
+
+		Ok(counts)
+	}
+
+	pub async fn insert_import_summary(
+		&self,
+		summary: &ImportSummary,
+		run_id: i32,
+		date: OffsetDateTime,
+	) -> Result<(), Box<dyn Error>> {
+		sqlx::query("INSERT INTO mod_import_summaries (date, added, updated, unchanged, failed, delisted, run_id) VALUES ($1, $2, $3, $4, $5, $6, $7);")
+			.bind(date)
+			.bind(summary.added)
+			.bind(summary.updated)
+			.bind(summary.unchanged)
+			.bind(summary.failed)
+			.bind(summary.delisted)
+			.bind(run_id)
+			.execute(&self.pool)
+			.await?;
+
+		Ok(())
+	}
+
+	pub async fn latest_import_summary(&self) -> Result<Option<ImportSummary>, Box<dyn Error>> {
+		let summary = sqlx::query_as(
+			"SELECT added, updated, unchanged, failed, delisted FROM mod_import_summaries ORDER BY date DESC LIMIT 1;",
+		)
+		.fetch_optional(&self.pool)
+		.await?;
+
+		Ok(summary)
+	}
+
+	/// rate a mod, an existing rating of the mod by the user is kept as is
+	pub async fn insert_mod_rating(
+		&self,
+		mod_id: &Uuid,
+		rating: &Rating,
+		user_id: i32,
+	) -> Result<(), Box<dyn Error>> {
+		sqlx::query("INSERT INTO ratings(mod_id, rating, user_id) VALUES ($1, $2, $3) ON CONFLICT (mod_id, user_id) DO NOTHING;")
+			.bind(mod_id)
+			.bind(rating)
+			.bind(user_id)
+			.execute(&self.pool)
+			.await?;
+		Ok(())
+	}
+
+	/// rate all the mods in a single statement, keeping existing ratings
+	pub async fn insert_mod_ratings(
+		&self,
+		mod_ids: &[Uuid],
+		rating: &Rating,
+		user_id: i32,
+	) -> Result<(), Box<dyn Error>> {
+		if mod_ids.is_empty() {
+			return Ok(());
+		}
+
+		let mut builder = QueryBuilder::new("INSERT INTO ratings(mod_id, rating, user_id) ");
+		builder.push_values(mod_ids, |mut b, mod_id| {
+			b.push_bind(mod_id);
+			b.push_bind(rating);
+			b.push_bind(user_id);
+		});
+		builder.push(" ON CONFLICT (mod_id, user_id) DO NOTHING;");
+
+		builder.build().execute(&self.pool).await?;
+		Ok(())
+	}
+
+	/// Change the user's existing rating of the mod, returns false if the user hasn't rated it.
+	/// The changed rating counts as the user's latest rating.
+	pub async fn update_mod_rating(
+		&self,
+		mod_id: &Uuid,
+		rating: &Rating,
+		user_id: i32,
+	) -> Result<bool, Box<dyn Error>> {
+		let result = sqlx::query(
+			"UPDATE ratings SET rating = $2, rated_at = now() WHERE mod_id = $1 AND user_id = $3;",
+		)
+		.bind(mod_id)
+		.bind(rating)
+		.bind(user_id)
+		.execute(&self.pool)
+		.await?;
+		Ok(result.rows_affected() > 0)
+	}
+
+	/// remove the user's rating of the mod, returns false if the user hasn't rated it
+	pub async fn delete_mod_rating(
+		&self,
+		mod_id: &Uuid,
+		user_id: i32,
+	) -> Result<bool, Box<dyn Error>> {
+		let result = sqlx::query("DELETE FROM ratings WHERE mod_id = $1 AND user_id = $2;")
+			.bind(mod_id)
+			.bind(user_id)
+			.execute(&self.pool)
+			.await?;
+		Ok(result.rows_affected() > 0)
+	}
+
+	/// Remove the user's most recent rating of a mod of the community, returns the mod whose
+	/// rating was removed. Ratings without a time can't be undone.
+	pub async fn undo_last_rating(
+		&self,
+		community: &str,
+		user_id: i32,
+	) -> Result<Option<Uuid>, Box<dyn Error>> {
+		let mod_id = sqlx::query_scalar(
+			"DELETE FROM ratings
+			WHERE user_id = $1 AND mod_id = (
+				SELECT ratings.mod_id FROM ratings
+				JOIN mods ON mods.id = ratings.mod_id
+				WHERE ratings.user_id = $1
+				AND mods.community = $2
+				AND ratings.rated_at IS NOT NULL
+				ORDER BY ratings.rated_at DESC
+				LIMIT 1
+			)
+			RETURNING mod_id;",
+		)
+		.bind(user_id)
+		.bind(community)
+		.fetch_optional(&self.pool)
+		.await?;
+		Ok(mod_id)
+	}
+
+	pub async fn get_mod_rating(
+		&self,
+		mod_id: &Uuid,
+		user_id: i32,
+	) -> Result<Option<Rating>, Box<dyn Error>> {
+		let rating =
+			sqlx::query_scalar("SELECT rating FROM ratings WHERE mod_id = $1 AND user_id = $2;")
+				.bind(mod_id)
+				.bind(user_id)
+				.fetch_optional(&self.pool)
+				.await?;
+		Ok(rating)
+	}
+
+	/// how many users of the instance have liked and disliked the mod
+	pub async fn get_rating_counts(&self, mod_id: &Uuid) -> Result<RatingCounts, Box<dyn Error>> {
+		let counts = sqlx::query_as(
+			"SELECT COUNT(*) FILTER (WHERE rating = 'Like') likes, COUNT(*) FILTER (WHERE rating = 'Dislike') dislikes
+			FROM ratings
+			WHERE mod_id = $1;",
+		)
+		.bind(mod_id)
+		.fetch_one(&self.pool)
+		.await?;
+		Ok(counts)
+	}
+
+	/// mods the user has rated with `rating`, most recently rated first
+	pub async fn get_rated_mods(
+		&self,
+		rating: &Rating,
+		community: &str,
+		limit: i16,
+		user_id: i32,
+	) -> Result<Vec<Mod>, Box<dyn Error>> {
+		let sql = format!(
+			"SELECT {MOD_COLUMNS}
+			FROM mods
+			JOIN ratings ON mods.id = ratings.mod_id
+			LEFT JOIN mod_category ON mod_category.mod_id = mods.id
+			LEFT JOIN categories ON categories.id = mod_category.category_id AND categories.community = mods.community
+			WHERE ratings.rating = $1
+			AND ratings.user_id = $2
+			AND mods.community = $4
+			GROUP BY mods.id, mods.community, ratings.rated_at
+			ORDER BY ratings.rated_at DESC NULLS LAST, mods.id
+			LIMIT $3;"
+		);
+
+		let mods = sqlx::query_as(&sql)
+			.bind(rating)
+			.bind(user_id)
+			.bind(limit)
+			.bind(community)
+			.fetch_all(&self.pool)
+			.await?;
+
+		Ok(mods)
+	}
+
+	/// ids of all mods of the community the user has rated with `rating`
+	pub async fn get_rated_mod_ids(
+		&self,
+		rating: &Rating,
+		community: &str,
+		user_id: i32,
+	) -> Result<Vec<Uuid>, Box<dyn Error>> {
+		let ids = sqlx::query_scalar(
+			"SELECT mods.id
+			FROM mods
+			JOIN ratings ON mods.id = ratings.mod_id
+			WHERE ratings.rating = $1
+			AND ratings.user_id = $2
+			AND mods.community = $3;",
+		)
+		.bind(rating)
+		.bind(user_id)
+		.bind(community)
+		.fetch_all(&self.pool)
+		.await?;
+
+		Ok(ids)
+	}
+
+	/// The user's ratings of mods of the community, one page at a time
+	pub async fn get_rating_history(
+		&self,
+		options: &RatingHistoryOptions,
+		user_id: i32,
+	) -> Result<Page<RatedMod>, Box<dyn Error>> {
+		let mut builder = QueryBuilder::new(format!(
+			"SELECT {MOD_COLUMNS}, ratings.rating, ratings.rated_at, COUNT(*) OVER () total
+			FROM mods
+			JOIN ratings ON ratings.mod_id = mods.id
+			LEFT JOIN mod_category ON mod_category.mod_id = mods.id
+			LEFT JOIN categories ON categories.id = mod_category.category_id AND categories.community = mods.community
+			WHERE ratings.user_id = "
+		));
+		builder.push_bind(user_id);
+		builder.push(" AND mods.community = ");
+		builder.push_bind(&options.community);
+		builder.push(" ");
+
+		if let Some(rating) = &options.rating {
+			builder.push("AND ratings.rating = ");
+			builder.push_bind(rating);
+			builder.push(" ");
+		}
+
+		if let Some(owner) = &options.owner {
+			builder.push("AND LOWER(mods.owner) = LOWER(");
+			builder.push_bind(owner);
+			builder.push(") ");
+		}
+
+		if let Some(category) = &options.category {
+			builder.push(
+				"AND mods.id IN
+					(SELECT mod_category.mod_id FROM mod_category
+					JOIN categories ON categories.id = mod_category.category_id
+					WHERE categories.community = ",
+			);
+			builder.push_bind(&options.community);
+			builder.push(" AND categories.name = ");
+			builder.push_bind(category);
+			builder.push(") ");
+		}
+
+		builder
+			.push("GROUP BY mods.id, mods.community, ratings.rating, ratings.rated_at ORDER BY ");
+		builder.push(match options.sort {
+			HistorySort::Name => "LOWER(mods.name), ",
+			HistorySort::Rated => "ratings.rated_at DESC NULLS LAST, ",
+			HistorySort::Updated => "mods.updated_date DESC, ",
+		});
+
+		let query = builder
+			.push("mods.id ")
+			.push("LIMIT ")
+			.push_bind(options.limit)
+			.push(" OFFSET ")
+			.push_bind(options.offset)
+			.build();
+
+		let rows = query.fetch_all(&self.pool).await?;
+		let total = match rows.first() {
+			Some(row) => row.try_get("total")?,
+			None => 0,
+		};
+		let items = rows
+			.iter()
+			.map(RatedMod::from_row)
+			.collect::<Result<_, _>>()?;
+
+		Ok(Page { items, total })
+	}
+
+	/// mods of the community matching the given full names, names without a matching mod are omitted
+	pub async fn find_mods_by_full_name(
+		&self,
+		full_names: &[String],
+		community: &str,
+		user_id: i32,
+	) -> Result<Vec<FullNameMatch>, Box<dyn Error>> {
+		let sql = "SELECT mods.full_name, mods.id, ratings.rating
+			FROM mods
+			LEFT JOIN ratings ON ratings.mod_id = mods.id AND ratings.user_id = $2
+			WHERE mods.full_name = ANY($1)
+			AND mods.community = $3
+			ORDER BY mods.full_name;";
+
+		let mods = sqlx::query_as(sql)
+			.bind(full_names)
+			.bind(user_id)
+			.bind(community)
+			.fetch_all(&self.pool)
+			.await?;
+
+		Ok(mods)
+	}
+
+	/// Mods of the community matching a full-text search, best matches first, along with the
+	/// user's rating of them and the number of matching mods. Without search terms, the mods of
+	/// the selected categories are listed by downloads.
+	pub async fn search_mods(
+		&self,
+		options: &ModSearchOptions,
+		user_id: i32,
+	) -> Result<Page<SearchResult>, Box<dyn Error>> {
+		let mut builder = QueryBuilder::new(format!(
+			"SELECT {MOD_COLUMNS}, ratings.rating, COUNT(*) OVER () total
+			FROM mods
+			LEFT JOIN ratings ON ratings.mod_id = mods.id AND ratings.user_id = "
+		));
+		builder.push_bind(user_id);
+		builder.push(
+			" LEFT JOIN mod_category ON mod_category.mod_id = mods.id
+			LEFT JOIN categories ON categories.id = mod_category.category_id AND categories.community = mods.community
+			WHERE mods.delisted_date IS NULL
+			AND mods.community = ",
+		);
+		builder.push_bind(&options.community);
+		builder.push(" ");
+
+		let terms = options.query.trim();
+		if !terms.is_empty() {
+			builder.push("AND mods.search_vector @@ websearch_to_tsquery('simple', ");
+			builder.push_bind(terms);
+			builder.push(") ");
+		}
+
+		if !options.include_deprecated {
+			builder.push("AND mods.deprecated = false ");
+		}
+
+		if !options.include_nsfw {
+			builder.push("AND mods.nsfw = false ");
+		}
+
+		if !options.categories.is_empty() {
+			builder.push(
+				"AND mods.id IN
+					(SELECT mod_category.mod_id FROM mod_category
+					JOIN categories ON categories.id = mod_category.category_id
+					WHERE categories.community = ",
+			);
+			builder.push_bind(&options.community);
+			builder.push(" AND categories.name IN ");
+
+			builder.push_tuples(&options.categories, |mut b, category| {
+				b.push_bind(category);
+			});
+
+			builder.push(") ");
+		}
+
+		builder.push("GROUP BY mods.id, mods.community, ratings.rating ORDER BY ");
+		if !terms.is_empty() {
+			builder.push("ts_rank(mods.search_vector, websearch_to_tsquery('simple', ");
+			builder.push_bind(terms);
+			builder.push(")) DESC, ");
+		}
+
+		let query = builder
+			.push("mods.downloads DESC, mods.name ")
+			.push("LIMIT ")
+			.push_bind(options.limit)
+			.push(" OFFSET ")
+			.push_bind(options.offset)
+			.build();
+
+		let rows = query.fetch_all(&self.pool).await?;
+		let total = match rows.first() {
+			Some(row) => row.try_get("total")?,
+			None => 0,
+		};
+		let items = rows
+			.iter()
+			.map(SearchResult::from_row)
+			.collect::<Result<_, _>>()?;
+
+		Ok(Page { items, total })
+	}
+
+	/// return the created user on success, return None if username was already taken
+	pub async fn insert_user(&self, user: &UserNoId) -> Result<Option<User>, Box<dyn Error>> {
+		let result = sqlx::query_as(
+			"INSERT INTO users(username, password_hash) VALUES ($1, $2) ON CONFLICT DO NOTHING RETURNING id, username, password_hash, has_import_privileges;",
+		)
+		.bind(&user.username)
+		.bind(&user.password_hash)
+		.fetch_optional(&self.pool).await?;
+
+		Ok(result)
+	}
+
+	pub async fn find_user(&self, name: &str) -> Result<Option<User>, Box<dyn Error>> {
+		let result = sqlx::query_as(
+			"SELECT id, username, password_hash, has_import_privileges FROM users WHERE username = $1;",
+		)
+		.bind(name)
+		.fetch_optional(&self.pool)
+		.await?;
+
+		Ok(result)
+	}
+
+	pub async fn find_user_by_id(&self, id: i32) -> Result<Option<User>, Box<dyn Error>> {
+		let result = sqlx::query_as(
+			"SELECT id, username, password_hash, has_import_privileges FROM users WHERE id = $1;",
+		)
+		.bind(id)
+		.fetch_optional(&self.pool)
+		.await?;
+
+		Ok(result)
+	}
 }
 
-struct InsertModCategory<'a> {
-	mod_id: &'a Uuid,
-	category_id: &'a i32,
-}
+pub struct InsertMod<'a> {
+	pub uuid4: Uuid,
+	pub name: &'a String,
+	pub description: &'a str,
+	pub icon_url: &'a str,
+	pub full_name: &'a String,
+	pub owner: &'a String,
+	pub package_url: &'a String,
+	pub updated_date: OffsetDateTime,
+	pub date_created: OffsetDateTime,
+	pub donation_link: Option<&'a str>,
+	pub is_pinned: bool,
+	pub website_url: Option<&'a str>,
+	pub file_size: i64,
+	pub downloads: i64,
+	pub rating: i64,
+	pub is_deprecated: bool,
+	pub has_nsfw_content: bool,
+	pub fingerprint: String,
+	pub community: &'a str,
+	pub category_ids: HashSet<&'a i32>,
+	pub versions: Vec<InsertModVersion<'a>>,
+	pub dependencies: Vec<InsertModDependency<'a>>,
+}
+
+pub struct InsertModVersion<'a> {
+	pub uuid4: Uuid,
+	pub mod_id: Uuid,
+	pub version_number: &'a String,
+	pub description: &'a String,
+	pub downloads: i64,
+	pub file_size: i64,
+	pub download_url: &'a String,
+	pub date_created: OffsetDateTime,
+	pub is_active: bool,
+}
+
+pub struct InsertModDependency<'a> {
+	pub mod_id: Uuid,
+	pub full_name: &'a str,
+	pub version: &'a str,
+}
+
+struct InsertModCategory<'a> {
+	mod_id: &'a Uuid,
+	category_id: &'a i32,
+}
+
+pub struct ModQueryOptions {
+	pub community: String,
+	pub ignored_categories: HashSet<String>,
+	pub limit: i32,
+	pub include_deprecated: bool,
+	pub include_nsfw: bool,
+	pub order: QueueOrder,
+}
+
+/// Order of the mods waiting to be rated
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueOrder {
+	/// most recently updated first, the same for everyone
+	#[default]
+	Updated,
+	/// mods liked by users with a similar taste first, see `Database::get_mods`
+	Recommended,
+}
+
+pub struct ModSearchOptions {
+	pub community: String,
+	/// search terms in the syntax of web search engines, e.g. `suits -"more suits"`
+	pub query: String,
+	/// only mods in any of these categories, all mods if empty
+	pub categories: HashSet<String>,
+	pub include_deprecated: bool,
+	pub include_nsfw: bool,
+	pub limit: i64,
+	pub offset: i64,
+}
+
+/// Order of the rating history
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistorySort {
+	/// alphabetically by mod name
+	Name,
+	/// most recently rated first
+	#[default]
+	Rated,
+	/// most recently updated mods first
+	Updated,
+}
+
+pub struct RatingHistoryOptions {
+	pub community: String,
+	/// only mods with this rating, both likes and dislikes if None
+	pub rating: Option<Rating>,
+	pub category: Option<String>,
+	/// only mods by this owner, case insensitive
+	pub owner: Option<String>,
+	pub sort: HistorySort,
+	pub limit: i64,
+	pub offset: i64,
+}
+
+impl Default for ModQueryOptions {
+	fn default() -> Self {
+		Self {
+			community: DEFAULT_COMMUNITY.to_string(),
+			ignored_categories: Default::default(),
+			limit: 20,
+			include_deprecated: false,
+			include_nsfw: false,
+			order: QueueOrder::Updated,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use time::format_description::well_known::Iso8601;
+
+	fn hashset_of(items: Vec<&str>) -> HashSet<String> {
+		items.into_iter().map(|s| s.to_string()).collect()
+	}
+
+	fn mod_names(mods: Vec<Mod>) -> HashSet<String> {
+		mods.into_iter().map(|m| m.name).collect()
+	}
+
+	#[sqlx::test(fixtures("mods", "users"))]
+	async fn querying_mods_without_ignored_categories(pool: Pool<Postgres>) {
+		let db = Database { pool };
+
+		let query_options = ModQueryOptions {
+			community: DEFAULT_COMMUNITY.to_string(),
+			ignored_categories: Default::default(),
+			limit: 100,
+			include_deprecated: true,
+			include_nsfw: true,
+			order: QueueOrder::Updated,
+		};
+
+		let result = db.get_mods(&query_options, 0).await.unwrap();
+
+		let expected = hashset_of(vec![
+			"1st",
+			"dep-mod",
+			"nsfw-mod",
+			"dep-nsfw",
+			"5th",
+			"6th",
+			"nsfw-2",
+			"no-category",
+			"new-update",
+			"old-mod",
+		]);
+
+		let mod_names = mod_names(result);
+		assert_eq!(expected, mod_names);
+	}
+
+	#[sqlx::test(fixtures("mods", "users"))]
+	async fn querying_mods_ignored_categories(pool: Pool<Postgres>) {
+		let db = Database { pool };
+
+		let query_options = ModQueryOptions {
+			community: DEFAULT_COMMUNITY.to_string(),
+			ignored_categories: hashset_of(vec!["Items", "Misc"]),
+			limit: 100,
+			include_deprecated: true,
+			include_nsfw: true,
+			order: QueueOrder::Updated,
+		};
+
+		let result = db.get_mods(&query_options, 0).await.unwrap();
+
+		let expected = hashset_of(vec!["6th", "no-category", "new-update", "old-mod"]);
+
+		let mod_names = mod_names(result);
+		assert_eq!(expected, mod_names);
+	}
+
+	#[sqlx::test(fixtures("mods", "users"))]
+	async fn querying_mods_allowing_deprecated(pool: Pool<Postgres>) {
+		let db = Database { pool };
+
+		let query_options = ModQueryOptions {
+			community: DEFAULT_COMMUNITY.to_string(),
+			ignored_categories: Default::default(),
+			limit: 100,
+			include_deprecated: true,
+			include_nsfw: false,
+			order: QueueOrder::Updated,
+		};
+
+		let result = db.get_mods(&query_options, 0).await.unwrap();
+
+		let expected = hashset_of(vec![
+			"1st",
+			"dep-mod",
+			"5th",
+			"6th",
+			"no-category",
+			"new-update",
+			"old-mod",
+		]);
+
+		let mod_names = mod_names(result);
+		assert_eq!(expected, mod_names);
+	}
+
+	#[sqlx::test(fixtures("mods", "users"))]
+	async fn querying_non_deprecated_mods(pool: Pool<Postgres>) {
+		let db = Database { pool };
+
+		let query_options = ModQueryOptions {
+			community: DEFAULT_COMMUNITY.to_string(),
+			ignored_categories: Default::default(),
+			limit: 100,
+			include_deprecated: false,
+			include_nsfw: false,
+			order: QueueOrder::Updated,
+		};
+
+		let result = db.get_mods(&query_options, 0).await.unwrap();
+
+		let expected = hashset_of(vec![
+			"1st",
+			"5th",
+			"6th",
+			"no-category",
+			"new-update",
+			"old-mod",
+		]);
+
+		let mod_names = mod_names(result);
+		assert_eq!(expected, mod_names);
+	}
+
+	#[sqlx::test(fixtures("mods", "users"))]
+	async fn querying_non_deprecated_mods_ignoring_categories(pool: Pool<Postgres>) {
+		let db = Database { pool };
+
+		let query_options = ModQueryOptions {
+			community: DEFAULT_COMMUNITY.to_string(),
+			ignored_categories: hashset_of(vec!["Music", "Suits"]),
+			limit: 100,
+			include_deprecated: false,
+			include_nsfw: false,
+			order: QueueOrder::Updated,
+		};
+
+		let result = db.get_mods(&query_options, 0).await.unwrap();
+
+		let expected = hashset_of(vec!["1st", "no-category", "new-update", "old-mod"]);
+
+		let mod_names = mod_names(result);
+		assert_eq!(expected, mod_names);
+	}
+
+	#[sqlx::test(fixtures("mods", "users"))]
+	async fn querying_non_deprecated_nswf_mods_ignoring_categories(pool: Pool<Postgres>) {
+		let db = Database { pool };
+
+		let query_options = ModQueryOptions {
+			community: DEFAULT_COMMUNITY.to_string(),
+			ignored_categories: hashset_of(vec!["TV", "Suits", "Misc"]),
+			limit: 100,
+			include_deprecated: false,
+			include_nsfw: true,
+			order: QueueOrder::Updated,
+		};
+
+		let result = db.get_mods(&query_options, 0).await.unwrap();
+
+		let expected = hashset_of(vec![
+			"nsfw-mod",
+			"6th",
+			"no-category",
+			"new-update",
+			"old-mod",
+		]);
+
+		let mod_names = mod_names(result);
+		assert_eq!(expected, mod_names);
+	}
+
+	#[sqlx::test(fixtures("mods", "users"))]
+	async fn querying_mods_most_recently_updated_is_first(pool: Pool<Postgres>) {
+		let db = Database { pool };
+
+		let query_options = ModQueryOptions {
+			community: DEFAULT_COMMUNITY.to_string(),
+			ignored_categories: Default::default(),
+			limit: 4,
+			include_deprecated: false,
+			include_nsfw: false,
+			order: QueueOrder::Updated,
+		};
+
+		let result = db.get_mods(&query_options, 0).await.unwrap();
+
+		let expected = vec!["new-update", "1st", "5th", "6th"];
+
+		let mods = result.iter().map(|m| m.name.as_str()).collect::<Vec<_>>();
+		assert_eq!(expected, mods);
+	}
+
+	#[sqlx::test]
+	async fn get_mod_import_date_from_empty_database(pool: Pool<Postgres>) {
+		let db = Database { pool };
+
+		let date = db.latest_mod_import_date().await.unwrap();
+		assert_eq!(None, date);
+	}
+
+	#[sqlx::test]
+	async fn set_and_get_mod_import_date(pool: Pool<Postgres>) {
+		let db = Database { pool };
+
+		let timestamp =
+			OffsetDateTime::parse("2025-03-22T12:45:56.001122Z", &Iso8601::DEFAULT).unwrap();
+		db.set_mods_imported_date(timestamp).await.unwrap();
+
+		let date = db.latest_mod_import_date().await.unwrap().unwrap();
+		assert_eq!(timestamp, date);
+	}
+
+	#[sqlx::test]
+	async fn set_mod_import_date_multiple_times(pool: Pool<Postgres>) {
+		let db = Database { pool };
+
+		let old = OffsetDateTime::parse("2000-01-01T00:00:00.000000Z", &Iso8601::DEFAULT).unwrap();
+		let mid = OffsetDateTime::parse("2002-02-22T00:00:00.000000Z", &Iso8601::DEFAULT).unwrap();
+		let new = OffsetDateTime::parse("2025-03-03T03:03:03.000000Z", &Iso8601::DEFAULT).unwrap();
+
+		db.set_mods_imported_date(old).await.unwrap();
+		db.set_mods_imported_date(mid).await.unwrap();
+		db.set_mods_imported_date(new).await.unwrap();
+
+		let date = db.latest_mod_import_date().await.unwrap().unwrap();
+		assert_eq!(new, date);
+	}
+
+	#[sqlx::test]
+	async fn recording_import_runs(pool: Pool<Postgres>) {
+		let db = Database { pool };
+
+		let failed = db
+			.start_import_run(ImportTrigger::Startup, ImportSource::Download)
+			.await
+			.unwrap();
+		db.finish_import_run(failed, Some("no connection"))
+			.await
+			.unwrap();
+
+		let succeeded = db
+			.start_import_run(ImportTrigger::Manual, ImportSource::Cache)
+			.await
+			.unwrap();
+		let summary = ImportSummary {
+			added: 1,
+			updated: 2,
+			unchanged: 3,
+			failed: 4,
+			delisted: 5,
+		};
+		db.insert_import_summary(&summary, succeeded, OffsetDateTime::now_utc())
+			.await
+			.unwrap();
+		db.finish_import_run(succeeded, None).await.unwrap();
+
+		let interrupted = db
+			.start_import_run(ImportTrigger::Expiration, ImportSource::Download)
+			.await
+			.unwrap();
+		db.fail_interrupted_import_runs().await.unwrap();
+
+		let runs = db.get_import_runs(10).await.unwrap();
+		let runs = runs
+			.iter()
+			.map(|run| {
+				(
+					run.id,
+					run.trigger,
+					run.outcome,
+					run.error.as_deref(),
+					run.added,
+				)
+			})
+			.collect::<Vec<_>>();
+
+		let expected = vec![
+			(
+				interrupted,
+				ImportTrigger::Expiration,
+				ImportOutcome::Failure,
+				Some("Interrupted by server shutdown"),
+				None,
+			),
+			(
+				succeeded,
+				ImportTrigger::Manual,
+				ImportOutcome::Success,
+				None,
+				Some(1),
+			),
+			(
+				failed,
+				ImportTrigger::Startup,
+				ImportOutcome::Failure,
+				Some("no connection"),
+				None,
+			),
+		];
+		assert_eq!(expected, runs);
+
+		let latest_failure = db.latest_failed_import_date().await.unwrap().unwrap();
+		let latest_run = db.get_import_runs(1).await.unwrap().remove(0);
+		assert_eq!(latest_run.started_at, latest_failure);
+	}
+
+	#[sqlx::test]
+	async fn counting_import_problems(pool: Pool<Postgres>) {
+		let db = Database { pool };
+		let run_id = db
+			.start_import_run(ImportTrigger::Manual, ImportSource::Cache)
+			.await
+			.unwrap();
+
+		let problem = |mod_name: &str, kind| ImportProblem {
+			community: DEFAULT_COMMUNITY.to_string(),
+			mod_name: mod_name.to_string(),
+			mod_id: String::new(),
+			kind,
+			reason: String::new(),
+		};
+		let problems = vec![
+			problem("a", ImportProblemKind::NoVersions),
+			problem("b", ImportProblemKind::InvalidDate),
+			problem("c", ImportProblemKind::InvalidDate),
+		];
+		db.insert_import_problems(run_id, &problems, 2)
+			.await
+			.unwrap();
+
+		let counts = db.count_import_problems(run_id).await.unwrap();
+		let expected = vec![
+			(ImportProblemKind::InvalidDate, 2),
+			(ImportProblemKind::NoVersions, 1),
+		];
+		assert_eq!(expected, counts);
+
+		let runs = db.get_import_runs(1).await.unwrap();
+		assert_eq!(3, runs[0].problems);
+	}
+
+	#[sqlx::test]
+	async fn insert_and_query_categories(pool: Pool<Postgres>) {
+		let db = Database { pool };
+		let categories = hashset_of(vec!["Foo", "Bar", "Baz", "Cat", "Dog"]);
+		let mut tx = db.begin_import(ImportProgress::default()).await.unwrap();
+		tx.insert_categories(DEFAULT_COMMUNITY, &categories)
+			.await
+			.unwrap();
+		tx.commit().await.unwrap();
+
+		let result = db
+			.get_categories(DEFAULT_COMMUNITY)
+			.await
+			.unwrap()
+			.into_iter()
+			.map(|ctg| ctg.name)
+			.collect::<HashSet<_>>();
+		let expected = hashset_of(vec!["Foo", "Bar", "Baz", "Cat", "Dog"]);
+
+		assert_eq!(expected, result);
+	}
+
+	#[sqlx::test(fixtures("users"))]
+	async fn inserting_and_querying_mods(pool: Pool<Postgres>) {
+		let null = "".to_string();
+
+		let db = Database { pool };
+		let mut tx = db.begin_import(ImportProgress::default()).await.unwrap();
+		tx.insert_categories(
+			DEFAULT_COMMUNITY,
+			&hashset_of(vec!["first", "second", "third"]),
+		)
+		.await
+		.unwrap();
+		tx.commit().await.unwrap();
+		let categories = db.get_categories(DEFAULT_COMMUNITY).await.unwrap();
+
+		let m1 = Mod {
+			name: "mod-1".to_string(),
+			owner: "cat".to_string(),
+			description: "first mod".to_string(),
+			icon_url: "icon-1 url".to_string(),
+			package_url: "package-1 url".to_string(),
+			id: Uuid::parse_str("aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa").unwrap(),
+			delisted: false,
+			categories: vec![
+				"first".to_string(),
+				"second".to_string(),
+				"third".to_string(),
+			],
+			updated_date: OffsetDateTime::parse("2025-03-22T19:59:59.012345Z", &Iso8601::DEFAULT)
+				.unwrap(),
+			date_created: OffsetDateTime::parse("2025-01-01T10:00:00.000000Z", &Iso8601::DEFAULT)
+				.unwrap(),
+			donation_link: Some("donation url".to_string()),
+			pinned: true,
+			website_url: Some("website url".to_string()),
+			file_size: 2000,
+			downloads: 300,
+			icon_file: None,
+		};
+
+		let m2 = Mod {
+			name: "mod-2".to_string(),
+			owner: "dog".to_string(),
+			description: "second mod".to_string(),
+			icon_url: "icon-2 url".to_string(),
+			package_url: "package-2 url".to_string(),
+			id: Uuid::parse_str("bbbbbbbb-bbbb-bbbb-bbbb-bbbbbbbbbbbb").unwrap(),
+			delisted: false,
+			categories: Default::default(),
+			updated_date: OffsetDateTime::parse("2025-03-22T22:22:22.222222Z", &Iso8601::DEFAULT)
+				.unwrap(),
+			date_created: OffsetDateTime::parse("2025-02-02T10:00:00.000000Z", &Iso8601::DEFAULT)
+				.unwrap(),
+			donation_link: None,
+			pinned: false,
+			website_url: None,
+			file_size: 0,
+			downloads: 0,
+			icon_file: None,
+		};
+
+		let mods = vec![
+			InsertMod {
+				uuid4: m1.id,
+				name: &m1.name,
+				description: &m1.description,
+				icon_url: &m1.icon_url,
+				owner: &m1.owner,
+				package_url: &m1.package_url,
+				full_name: &null,
+				updated_date: m1.updated_date,
+				date_created: m1.date_created,
+				donation_link: m1.donation_link.as_deref(),
+				is_pinned: m1.pinned,
+				website_url: m1.website_url.as_deref(),
+				file_size: m1.file_size,
+				downloads: m1.downloads,
+				rating: 12345,
+				is_deprecated: false,
+				has_nsfw_content: false,
+				fingerprint: String::new(),
+				community: DEFAULT_COMMUNITY,
+				category_ids: HashSet::from_iter(vec![
+					&categories.first().unwrap().id,
+					&categories.get(1).unwrap().id,
+					&categories.get(2).unwrap().id,
+				]),
+				versions: vec![],
+				dependencies: vec![],
+			},
+			InsertMod {
+				uuid4: m2.id,
+				name: &m2.name,
+				description: &m2.description,
+				icon_url: &m2.icon_url,
+				owner: &m2.owner,
+				package_url: &m2.package_url,
+				full_name: &null,
+				updated_date: m2.updated_date,
+				date_created: m2.date_created,
+				donation_link: m2.donation_link.as_deref(),
+				is_pinned: m2.pinned,
+				website_url: m2.website_url.as_deref(),
+				file_size: m2.file_size,
+				downloads: m2.downloads,
+				rating: 54321,
+				is_deprecated: true,
+				has_nsfw_content: true,
+				fingerprint: String::new(),
+				community: DEFAULT_COMMUNITY,
+				category_ids: HashSet::from_iter(vec![]),
+				versions: vec![],
+				dependencies: vec![],
+			},
+		];
+
+		let mut tx = db.begin_import(ImportProgress::default()).await.unwrap();
+		tx.insert_mods(DEFAULT_COMMUNITY, &mods, 150).await.unwrap();
+		tx.commit().await.unwrap();
+
+		let query_options = ModQueryOptions {
+			community: DEFAULT_COMMUNITY.to_string(),
+			ignored_categories: Default::default(),
+			limit: 100,
+			include_deprecated: true,
+			include_nsfw: true,
+			order: QueueOrder::Updated,
+		};
+
+		let mut result = db.get_mods(&query_options, 0).await.unwrap();
+		result.sort_by(|a, b| a.name.cmp(&b.name));
+
+		let mut expected = vec![m1, m2];
+		expected.sort_by(|a, b| a.name.cmp(&b.name));
+
+		// categories might be returned in random order and FromRow doesn't support HashSet
+		// -> sort them to prevent tests from failing randomly
+		result.iter_mut().for_each(|modd| modd.categories.sort());
+		expected.iter_mut().for_each(|modd| modd.categories.sort());
+		assert_eq!(expected, result);
+	}
+
+	#[sqlx::test(fixtures("mods"))]
+	async fn caching_icons(pool: Pool<Postgres>) {
+		sqlx::query("UPDATE mods SET icon_url = 'icon-' || name WHERE name IN ('1st', '5th');")
+			.execute(&pool)
+			.await
+			.unwrap();
+		let db = Database { pool };
+		let first = Uuid::parse_str("00000000-0000-0000-0000-000000000001").unwrap();
+		let fifth = Uuid::parse_str("00000000-0000-0000-0000-000000000005").unwrap();
+
+		let missing = db.get_mods_without_cached_icon().await.unwrap();
+		let expected = vec![
+			(first, "icon-1st".to_string()),
+			(fifth, "icon-5th".to_string()),
+		];
+		assert_eq!(expected, missing);
+
+		db.set_cached_icon(&first, "icon-1st", Some("first.png"))
+			.await
+			.unwrap();
+		// an icon that can't be cached isn't fetched again either
+		db.set_cached_icon(&fifth, "icon-5th", None).await.unwrap();
+		assert!(db.get_mods_without_cached_icon().await.unwrap().is_empty());
+		assert_eq!(
+			Some("first.png".to_string()),
+			db.get_icon_file(&first).await.unwrap()
+		);
+		assert_eq!(None, db.get_icon_file(&fifth).await.unwrap());
+		assert_eq!(
+			HashSet::from(["first.png".to_string()]),
+			db.get_icon_files().await.unwrap()
+		);
+
+		// changed icons are fetched again
+		sqlx::query("UPDATE mods SET icon_url = 'new-icon' WHERE id = $1;")
+			.bind(first)
+			.execute(&db.pool)
+			.await
+			.unwrap();
+		let missing = db.get_mods_without_cached_icon().await.unwrap();
+		assert_eq!(vec![(first, "new-icon".to_string())], missing);
+	}
+
+	#[sqlx::test]
+	async fn inserting_and_querying_mod_versions(pool: Pool<Postgres>) {
+		let null = "".to_string();
+		let old_version = "1.0.0".to_string();
+		let new_version = "1.1.0".to_string();
+		let description = "a mod".to_string();
+		let download_url = "download url".to_string();
+
+		let db = Database { pool };
+
+		let mod_id = Uuid::parse_str("aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa").unwrap();
+		let old_date =
+			OffsetDateTime::parse("2025-01-01T10:00:00.000000Z", &Iso8601::DEFAULT).unwrap();
+		let new_date =
+			OffsetDateTime::parse("2025-02-02T10:00:00.000000Z", &Iso8601::DEFAULT).unwrap();
+
+		let mods = vec![InsertMod {
+			uuid4: mod_id,
+			name: &null,
+			description: &description,
+			icon_url: &null,
+			owner: &null,
+			package_url: &null,
+			full_name: &null,
+			updated_date: new_date,
+			date_created: old_date,
+			donation_link: None,
+			is_pinned: false,
+			website_url: None,
+			file_size: 0,
+			downloads: 0,
+			rating: 0,
+			is_deprecated: false,
+			has_nsfw_content: false,
+			fingerprint: String::new(),
+			community: DEFAULT_COMMUNITY,
+			category_ids: HashSet::new(),
+			versions: vec![
+				InsertModVersion {
+					uuid4: Uuid::parse_str("00000000-0000-0000-0000-00000000000a").unwrap(),
+					mod_id,
+					version_number: &old_version,
+					description: &description,
+					downloads: 100,
+					file_size: 2000,
+					download_url: &download_url,
+					date_created: old_date,
+					is_active: false,
+				},
+				InsertModVersion {
+					uuid4: Uuid::parse_str("00000000-0000-0000-0000-00000000000b").unwrap(),
+					mod_id,
+					version_number: &new_version,
+					description: &description,
+					downloads: 5,
+					file_size: 3000,
+					download_url: &download_url,
+					date_created: new_date,
+					is_active: true,
+				},
+			],
+			dependencies: vec![],
+		}];
+
+		let mut tx = db.begin_import(ImportProgress::default()).await.unwrap();
+		tx.insert_mods(DEFAULT_COMMUNITY, &mods, 1).await.unwrap();
+		tx.commit().await.unwrap();
+
+		let result = db.get_mod_versions(&mod_id).await.unwrap();
+		let expected = vec![
+			Version {
+				version_number: new_version.clone(),
+				description: description.clone(),
+				downloads: 5,
+				file_size: 3000,
+				download_url: download_url.clone(),
+				date_created: new_date,
+				is_active: true,
+			},
+			Version {
+				version_number: old_version.clone(),
+				description: description.clone(),
+				downloads: 100,
+				file_size: 2000,
+				download_url: download_url.clone(),
+				date_created: old_date,
+				is_active: false,
+			},
+		];
 
-pub struct ModQueryOptions {
-	pub ignored_categories: HashSet<String>,
-	pub limit: i32,
-	pub include_deprecated: bool,
-	pub include_nsfw: bool,
-}
+		assert_eq!(expected, result);
+	}
 
-impl Default for ModQueryOptions {
-	fn default() -> Self {
-		Self {
-			ignored_categories: Default::default(),
-			limit: 20,
-			include_deprecated: false,
-			include_nsfw: false,
-		}
+	fn dependency_names(dependencies: &[Dependency]) -> Vec<&str> {
+		dependencies.iter().map(|d| d.full_name.as_str()).collect()
 	}
-}
 
-#[cfg(test)]
-mod tests {
-	use super::*;
-	use time::format_description::well_known::Iso8601;
+	#[sqlx::test(fixtures("mod_dependencies"))]
+	async fn querying_transitive_dependencies(pool: Pool<Postgres>) {
+		let db = Database { pool };
 
-	fn hashset_of(items: Vec<&str>) -> HashSet<String> {
-		items.into_iter().map(|s| s.to_string()).collect()
+		let suits = Uuid::parse_str("00000000-0000-0000-0000-0000000000a3").unwrap();
+		let result = db
+			.get_dependencies(&suits, DEFAULT_COMMUNITY)
+			.await
+			.unwrap();
+
+		let expected = vec![
+			Dependency {
+				full_name: "Lib-Api".to_string(),
+				mod_id: Some(Uuid::parse_str("00000000-0000-0000-0000-0000000000a2").unwrap()),
+				package_url: Some("api-url".to_string()),
+			},
+			Dependency {
+				full_name: "Lib-Pack".to_string(),
+				mod_id: Some(Uuid::parse_str("00000000-0000-0000-0000-0000000000a1").unwrap()),
+				package_url: Some("pack-url".to_string()),
+			},
+		];
+		assert_eq!(expected, result);
 	}
 
-	fn mod_names(mods: Vec<Mod>) -> HashSet<String> {
-		mods.into_iter().map(|m| m.name).collect()
+	#[sqlx::test(fixtures("mod_dependencies"))]
+	async fn querying_dependencies_of_multiple_mods(pool: Pool<Postgres>) {
+		let db = Database { pool };
+
+		let mod_ids = vec![
+			Uuid::parse_str("00000000-0000-0000-0000-0000000000a3").unwrap(),
+			Uuid::parse_str("00000000-0000-0000-0000-0000000000a5").unwrap(),
+		];
+		let result = db
+			.get_dependencies_of_mods(&mod_ids, DEFAULT_COMMUNITY)
+			.await
+			.unwrap();
+
+		// Dog-Cycle and Dog-Music depend on each other
+		let expected = vec![
+			"Dog-Cycle",
+			"Dog-Music",
+			"Gone-Missing",
+			"Lib-Api",
+			"Lib-Pack",
+		];
+		assert_eq!(expected, dependency_names(&result));
+
+		let missing = result
+			.iter()
+			.find(|d| d.full_name == "Gone-Missing")
+			.unwrap();
+		assert_eq!(None, missing.mod_id);
 	}
 
-	#[sqlx::test(fixtures("mods", "users"))]
-	async fn querying_mods_without_ignored_categories(pool: Pool<Postgres>) {
+	#[sqlx::test(fixtures("mod_dependencies"))]
+	async fn querying_dependencies_of_mod_without_dependencies(pool: Pool<Postgres>) {
+		let db = Database { pool };
+
+		let loner = Uuid::parse_str("00000000-0000-0000-0000-0000000000a6").unwrap();
+		let result = db
+			.get_dependencies(&loner, DEFAULT_COMMUNITY)
+			.await
+			.unwrap();
+
+		assert!(result.is_empty());
+	}
+
+	#[sqlx::test(fixtures("mod_dependencies"))]
+	async fn querying_unresolved_dependencies(pool: Pool<Postgres>) {
+		let db = Database { pool };
+
+		let result = db.get_unresolved_dependencies().await.unwrap();
+
+		let expected = vec![UnresolvedDependency {
+			mod_full_name: "Dog-Music".to_string(),
+			dependency_full_name: "Gone-Missing".to_string(),
+			dependency_version: "0.0.1".to_string(),
+		}];
+		assert_eq!(expected, result);
+	}
+
+	#[sqlx::test(fixtures("mod_dependencies", "communities"))]
+	async fn dependencies_are_resolved_within_community(pool: Pool<Postgres>) {
+		let db = Database { pool };
+
+		let era = Uuid::parse_str("00000000-0000-0000-0000-0000000000b2").unwrap();
+		let result = db.get_dependencies(&era, "content-warning").await.unwrap();
+
+		let expected = vec![
+			Dependency {
+				full_name: "Lib-Api".to_string(),
+				mod_id: None,
+				package_url: None,
+			},
+			Dependency {
+				full_name: "Lib-Pack".to_string(),
+				mod_id: Some(Uuid::parse_str("00000000-0000-0000-0000-0000000000b1").unwrap()),
+				package_url: Some("cw-pack-url".to_string()),
+			},
+		];
+		assert_eq!(expected, result);
+
+		let unresolved = db.get_unresolved_dependencies().await.unwrap();
+		let names = unresolved
+			.iter()
+			.map(|d| (d.mod_full_name.as_str(), d.dependency_full_name.as_str()))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			vec![("Cam-Era", "Lib-Api"), ("Dog-Music", "Gone-Missing")],
+			names
+		);
+	}
+
+	#[sqlx::test(fixtures("mod_dependencies", "mod_versions"))]
+	async fn querying_latest_versions(pool: Pool<Postgres>) {
+		let db = Database { pool };
+
+		let mod_ids = vec![
+			Uuid::parse_str("00000000-0000-0000-0000-0000000000a1").unwrap(),
+			Uuid::parse_str("00000000-0000-0000-0000-0000000000a2").unwrap(),
+			// no versions
+			Uuid::parse_str("00000000-0000-0000-0000-0000000000a6").unwrap(),
+		];
+		let result = db.get_latest_versions(&mod_ids).await.unwrap();
+
+		let expected = vec![
+			PackageVersion {
+				full_name: "Lib-Api".to_string(),
+				version_number: "1.0.0".to_string(),
+			},
+			PackageVersion {
+				full_name: "Lib-Pack".to_string(),
+				version_number: "5.4.2100".to_string(),
+			},
+		];
+		assert_eq!(expected, result);
+	}
+
+	#[sqlx::test(fixtures("mods", "users", "user_ratings"))]
+	async fn rated_mods_are_omitted_from_queries(pool: Pool<Postgres>) {
 		let db = Database { pool };
 
+		db.insert_mod_rating(
+			&Uuid::parse_str("00000000-0000-0000-0000-000000000005").unwrap(),
+			&Rating::Like,
+			0,
+		)
+		.await
+		.unwrap();
+		db.insert_mod_rating(
+			&Uuid::parse_str("00000000-0000-0000-0000-000000000006").unwrap(),
+			&Rating::Dislike,
+			0,
+		)
+		.await
+		.unwrap();
+
 		let query_options = ModQueryOptions {
+			community: DEFAULT_COMMUNITY.to_string(),
 			ignored_categories: Default::default(),
 			limit: 100,
 			include_deprecated: true,
 			include_nsfw: true,
+			order: QueueOrder::Updated,
 		};
 
 		let result = db.get_mods(&query_options, 0).await.unwrap();
 
+		let mods = mod_names(result);
 		let expected = hashset_of(vec![
 			"1st",
 			"dep-mod",
 			"nsfw-mod",
 			"dep-nsfw",
-			"5th",
-			"6th",
 			"nsfw-2",
 			"no-category",
 			"new-update",
 			"old-mod",
 		]);
 
-		let mod_names = mod_names(result);
-		assert_eq!(expected, mod_names);
+		assert_eq!(expected, mods);
 	}
 
-	#[sqlx::test(fixtures("mods", "users"))]
-	async fn querying_mods_ignored_categories(pool: Pool<Postgres>) {
+	#[sqlx::test(fixtures("mods", "users", "user_ratings"))]
+	async fn querying_rated_mods(pool: Pool<Postgres>) {
 		let db = Database { pool };
 
-		let query_options = ModQueryOptions {
-			ignored_categories: hashset_of(vec!["Items", "Misc"]),
-			limit: 100,
-			include_deprecated: true,
-			include_nsfw: true,
-		};
+		// user ratings fixture has an user with this id who has liked and disliked some mods
+		let user_with_ratings_id = 3;
+		let result = db
+			.get_rated_mods(&Rating::Like, DEFAULT_COMMUNITY, 100, user_with_ratings_id)
+			.await
+			.unwrap();
+		let result_ids = result.iter().map(|m| m.id).collect::<HashSet<_>>();
 
-		let result = db.get_mods(&query_options, 0).await.unwrap();
+		let mods = mod_names(result);
+		let expected = hashset_of(vec!["1st", "nsfw-mod", "new-update"]);
 
-		let expected = hashset_of(vec!["6th", "no-category", "new-update", "old-mod"]);
+		assert_eq!(expected, mods);
 
-		let mod_names = mod_names(result);
-		assert_eq!(expected, mod_names);
+		let ids = db
+			.get_rated_mod_ids(&Rating::Like, DEFAULT_COMMUNITY, user_with_ratings_id)
+			.await
+			.unwrap();
+		assert_eq!(result_ids, ids.into_iter().collect());
+
+		// most recently rated first
+		sqlx::raw_sql(
+			"UPDATE ratings SET rated_at = '2025-01-01T10:00:00Z' WHERE user_id = 3;
+			UPDATE ratings SET rated_at = '2025-03-01T10:00:00Z' WHERE user_id = 3 AND mod_id = '00000000-0000-0000-0000-000000000001';",
+		)
+		.execute(&db.pool)
+		.await
+		.unwrap();
+		let result = db
+			.get_rated_mods(&Rating::Like, DEFAULT_COMMUNITY, 1, user_with_ratings_id)
+			.await
+			.unwrap();
+		let names = result.into_iter().map(|m| m.name).collect::<Vec<_>>();
+		assert_eq!(vec!["1st"], names);
 	}
 
-	#[sqlx::test(fixtures("mods", "users"))]
-	async fn querying_mods_allowing_deprecated(pool: Pool<Postgres>) {
+	#[sqlx::test(fixtures("mods", "users", "user_ratings"))]
+	async fn searching_mods(pool: Pool<Postgres>) {
 		let db = Database { pool };
 
-		let query_options = ModQueryOptions {
-			ignored_categories: Default::default(),
-			limit: 100,
-			include_deprecated: true,
+		sqlx::raw_sql(
+			"UPDATE mods SET name = 'MoreSuits' WHERE id = '00000000-0000-0000-0000-000000000001';
+			UPDATE mods SET description = 'Plays music in the suits shop' WHERE id = '00000000-0000-0000-0000-000000000006';
+			UPDATE mods SET owner = 'suits' WHERE id = '00000000-0000-0000-0000-000000000003';",
+		)
+		.execute(&db.pool)
+		.await
+		.unwrap();
+
+		let options = ModSearchOptions {
+			community: DEFAULT_COMMUNITY.to_string(),
+			query: "suits".to_string(),
+			categories: Default::default(),
+			include_deprecated: false,
 			include_nsfw: false,
+			limit: 20,
+			offset: 0,
 		};
 
-		let result = db.get_mods(&query_options, 0).await.unwrap();
+		// user ratings fixture has an user with this id who has liked "1st"
+		let search = db.search_mods(&options, 3).await.unwrap();
+		let results = search
+			.items
+			.iter()
+			.map(|result| (result.modd.name.as_str(), result.rating))
+			.collect::<Vec<_>>();
+		// a match in the name ranks higher than one in the description, the nsfw mod is omitted
+		assert_eq!(
+			vec![("MoreSuits", Some(Rating::Like)), ("6th", None)],
+			results
+		);
+		assert_eq!(2, search.total);
 
-		let expected = hashset_of(vec![
-			"1st",
-			"dep-mod",
-			"5th",
-			"6th",
-			"no-category",
-			"new-update",
-			"old-mod",
-		]);
+		let second_page = ModSearchOptions {
+			limit: 1,
+			offset: 1,
+			..options
+		};
+		let search = db.search_mods(&second_page, 3).await.unwrap();
+		assert_eq!(
+			vec!["6th"],
+			search
+				.items
+				.iter()
+				.map(|r| r.modd.name.as_str())
+				.collect::<Vec<_>>()
+		);
+		assert_eq!(2, search.total);
 
-		let mod_names = mod_names(result);
-		assert_eq!(expected, mod_names);
+		let in_category = ModSearchOptions {
+			categories: hashset_of(vec!["Music"]),
+			limit: 20,
+			offset: 0,
+			..second_page
+		};
+		let search = db.search_mods(&in_category, 3).await.unwrap();
+		assert_eq!(
+			vec!["6th"],
+			search
+				.items
+				.iter()
+				.map(|r| r.modd.name.as_str())
+				.collect::<Vec<_>>()
+		);
+		assert_eq!(1, search.total);
+
+		let only_category = ModSearchOptions {
+			query: String::new(),
+			..in_category
+		};
+		let search = db.search_mods(&only_category, 3).await.unwrap();
+		let names = search
+			.items
+			.into_iter()
+			.map(|result| result.modd.name)
+			.collect::<HashSet<_>>();
+		assert_eq!(hashset_of(vec!["5th", "6th"]), names);
 	}
 
-	#[sqlx::test(fixtures("mods", "users"))]
-	async fn querying_non_deprecated_mods(pool: Pool<Postgres>) {
+	#[sqlx::test(fixtures("mods", "users", "user_ratings"))]
+	async fn getting_and_changing_ratings_of_a_mod(pool: Pool<Postgres>) {
 		let db = Database { pool };
 
-		let query_options = ModQueryOptions {
-			ignored_categories: Default::default(),
-			limit: 100,
-			include_deprecated: false,
-			include_nsfw: false,
-		};
+		let id = Uuid::parse_str("00000000-0000-0000-0000-000000000001").unwrap();
+		let modd = db.get_mod(&id, DEFAULT_COMMUNITY).await.unwrap().unwrap();
+		assert_eq!("1st", modd.name);
+		assert_eq!(
+			hashset_of(vec!["Items", "Misc"]),
+			modd.categories.into_iter().collect()
+		);
 
-		let result = db.get_mods(&query_options, 0).await.unwrap();
+		// user ratings fixture has two users who liked the mod and one who disliked it
+		let counts = db.get_rating_counts(&id).await.unwrap();
+		assert_eq!(
+			RatingCounts {
+				likes: 2,
+				dislikes: 1
+			},
+			counts
+		);
+		assert_eq!(Some(Rating::Like), db.get_mod_rating(&id, 3).await.unwrap());
+		assert_eq!(None, db.get_mod_rating(&id, 0).await.unwrap());
 
-		let expected = hashset_of(vec![
-			"1st",
-			"5th",
-			"6th",
-			"no-category",
-			"new-update",
-			"old-mod",
-		]);
+		assert!(
+			db.update_mod_rating(&id, &Rating::Dislike, 3)
+				.await
+				.unwrap()
+		);
+		assert!(
+			!db.update_mod_rating(&id, &Rating::Dislike, 0)
+				.await
+				.unwrap()
+		);
 
-		let mod_names = mod_names(result);
-		assert_eq!(expected, mod_names);
+		let counts = db.get_rating_counts(&id).await.unwrap();
+		assert_eq!(
+			RatingCounts {
+				likes: 1,
+				dislikes: 2
+			},
+			counts
+		);
+		assert_eq!(
+			Some(Rating::Dislike),
+			db.get_mod_rating(&id, 3).await.unwrap()
+		);
+		assert_eq!(None, db.get_mod_rating(&id, 0).await.unwrap());
+
+		let missing = Uuid::parse_str("00000000-0000-0000-0000-0000000000ff").unwrap();
+		assert_eq!(None, db.get_mod(&missing, DEFAULT_COMMUNITY).await.unwrap());
+		assert_eq!(None, db.get_mod(&id, "other-community").await.unwrap());
 	}
 
 	#[sqlx::test(fixtures("mods", "users"))]
-	async fn querying_non_deprecated_mods_ignoring_categories(pool: Pool<Postgres>) {
+	async fn rating_again_and_clearing_ratings(pool: Pool<Postgres>) {
 		let db = Database { pool };
 
-		let query_options = ModQueryOptions {
-			ignored_categories: hashset_of(vec!["Music", "Suits"]),
-			limit: 100,
-			include_deprecated: false,
-			include_nsfw: false,
-		};
-
-		let result = db.get_mods(&query_options, 0).await.unwrap();
+		let id = Uuid::parse_str("00000000-0000-0000-0000-000000000001").unwrap();
+		db.insert_mod_rating(&id, &Rating::Like, 0).await.unwrap();
+		// e.g. a form submitted twice, the first rating is kept
+		db.insert_mod_rating(&id, &Rating::Like, 0).await.unwrap();
+		db.insert_mod_rating(&id, &Rating::Dislike, 0)
+			.await
+			.unwrap();
+		assert_eq!(Some(Rating::Like), db.get_mod_rating(&id, 0).await.unwrap());
 
-		let expected = hashset_of(vec!["1st", "no-category", "new-update", "old-mod"]);
+		assert!(db.delete_mod_rating(&id, 0).await.unwrap());
+		assert!(!db.delete_mod_rating(&id, 0).await.unwrap());
+		assert_eq!(None, db.get_mod_rating(&id, 0).await.unwrap());
 
-		let mod_names = mod_names(result);
-		assert_eq!(expected, mod_names);
+		// the mod can be rated again after clearing its rating
+		db.insert_mod_rating(&id, &Rating::Dislike, 0)
+			.await
+			.unwrap();
+		assert_eq!(
+			Some(Rating::Dislike),
+			db.get_mod_rating(&id, 0).await.unwrap()
+		);
 	}
 
-	#[sqlx::test(fixtures("mods", "users"))]
-	async fn querying_non_deprecated_nswf_mods_ignoring_categories(pool: Pool<Postgres>) {
+	#[sqlx::test(fixtures("mods", "users", "user_ratings"))]
+	async fn undoing_latest_ratings(pool: Pool<Postgres>) {
 		let db = Database { pool };
 
-		let query_options = ModQueryOptions {
-			ignored_categories: hashset_of(vec!["TV", "Suits", "Misc"]),
-			limit: 100,
-			include_deprecated: false,
-			include_nsfw: true,
-		};
+		let first = Uuid::parse_str("00000000-0000-0000-0000-000000000001").unwrap();
+		let second = Uuid::parse_str("00000000-0000-0000-0000-000000000005").unwrap();
+		db.insert_mod_rating(&first, &Rating::Like, 0)
+			.await
+			.unwrap();
+		db.insert_mod_rating(&second, &Rating::Dislike, 0)
+			.await
+			.unwrap();
 
-		let result = db.get_mods(&query_options, 0).await.unwrap();
+		let undone = db.undo_last_rating(DEFAULT_COMMUNITY, 0).await.unwrap();
+		assert_eq!(Some(second), undone);
+		assert_eq!(None, db.get_mod_rating(&second, 0).await.unwrap());
 
-		let expected = hashset_of(vec![
-			"nsfw-mod",
-			"6th",
-			"no-category",
-			"new-update",
-			"old-mod",
-		]);
+		let undone = db.undo_last_rating(DEFAULT_COMMUNITY, 0).await.unwrap();
+		assert_eq!(Some(first), undone);
+		assert_eq!(
+			None,
+			db.undo_last_rating(DEFAULT_COMMUNITY, 0).await.unwrap()
+		);
 
-		let mod_names = mod_names(result);
-		assert_eq!(expected, mod_names);
+		// ratings of other communities and ratings without a time are left alone
+		db.insert_mod_rating(&first, &Rating::Like, 0)
+			.await
+			.unwrap();
+		assert_eq!(None, db.undo_last_rating("other-game", 0).await.unwrap());
+		// as if the user ratings fixture was rated before rating times were stored
+		sqlx::query("UPDATE ratings SET rated_at = NULL WHERE user_id = 3;")
+			.execute(&db.pool)
+			.await
+			.unwrap();
+		assert_eq!(
+			None,
+			db.undo_last_rating(DEFAULT_COMMUNITY, 3).await.unwrap()
+		);
 	}
 
 	#[sqlx::test(fixtures("mods", "users"))]
-	async fn querying_mods_most_recently_updated_is_first(pool: Pool<Postgres>) {
+	async fn undoing_changed_rating(pool: Pool<Postgres>) {
 		let db = Database { pool };
 
-		let query_options = ModQueryOptions {
-			ignored_categories: Default::default(),
-			limit: 4,
-			include_deprecated: false,
-			include_nsfw: false,
-		};
-
-		let result = db.get_mods(&query_options, 0).await.unwrap();
-
-		let expected = vec!["new-update", "1st", "5th", "6th"];
+		let first = Uuid::parse_str("00000000-0000-0000-0000-000000000001").unwrap();
+		let second = Uuid::parse_str("00000000-0000-0000-0000-000000000005").unwrap();
+		db.insert_mod_rating(&first, &Rating::Like, 0)
+			.await
+			.unwrap();
+		db.insert_mod_rating(&second, &Rating::Like, 0)
+			.await
+			.unwrap();
+		db.update_mod_rating(&first, &Rating::Dislike, 0)
+			.await
+			.unwrap();
 
-		let mods = result.iter().map(|m| m.name.as_str()).collect::<Vec<_>>();
-		assert_eq!(expected, mods);
+		let undone = db.undo_last_rating(DEFAULT_COMMUNITY, 0).await.unwrap();
+		assert_eq!(Some(first), undone);
+		assert_eq!(
+			Some(Rating::Like),
+			db.get_mod_rating(&second, 0).await.unwrap()
+		);
 	}
 
-	#[sqlx::test]
-	async fn get_mod_import_date_from_empty_database(pool: Pool<Postgres>) {
+	#[sqlx::test(fixtures("mods", "users", "user_ratings"))]
+	async fn querying_rating_history(pool: Pool<Postgres>) {
 		let db = Database { pool };
 
-		let date = db.latest_mod_import_date().await.unwrap();
-		assert_eq!(None, date);
-	}
+		sqlx::raw_sql(
+			"UPDATE ratings SET rated_at = '2025-04-01T00:00:00Z' WHERE user_id = 3 AND mod_id = '00000000-0000-0000-0000-000000000009';
+			UPDATE ratings SET rated_at = '2025-04-02T00:00:00Z' WHERE user_id = 3 AND mod_id = '00000000-0000-0000-0000-000000000001';
+			UPDATE ratings SET rated_at = NULL WHERE user_id = 3 AND mod_id = '00000000-0000-0000-0000-000000000003';
+			UPDATE mods SET owner = 'Someone' WHERE id = '00000000-0000-0000-0000-000000000009';",
+		)
+		.execute(&db.pool)
+		.await
+		.unwrap();
 
-	#[sqlx::test]
-	async fn set_and_get_mod_import_date(pool: Pool<Postgres>) {
-		let db = Database { pool };
+		let names = |page: Page<RatedMod>| {
+			page.items
+				.into_iter()
+				.map(|rated| rated.modd.name)
+				.collect::<Vec<_>>()
+		};
 
-		let timestamp =
-			OffsetDateTime::parse("2025-03-22T12:45:56.001122Z", &Iso8601::DEFAULT).unwrap();
-		db.set_mods_imported_date(timestamp).await.unwrap();
+		// user ratings fixture has an user with this id who has liked and disliked some mods
+		let options = RatingHistoryOptions {
+			community: DEFAULT_COMMUNITY.to_string(),
+			rating: None,
+			category: None,
+			owner: None,
+			sort: HistorySort::Name,
+			limit: 2,
+			offset: 2,
+		};
+		let history = db.get_rating_history(&options, 3).await.unwrap();
+		assert_eq!(6, history.total);
+		assert_eq!(vec!["new-update", "no-category"], names(history));
 
-		let date = db.latest_mod_import_date().await.unwrap().unwrap();
-		assert_eq!(timestamp, date);
+		let liked = RatingHistoryOptions {
+			rating: Some(Rating::Like),
+			sort: HistorySort::Rated,
+			limit: 100,
+			offset: 0,
+			..options
+		};
+		let history = db.get_rating_history(&liked, 3).await.unwrap();
+		// ratings without a time come last
+		assert_eq!(vec!["1st", "new-update", "nsfw-mod"], names(history));
+
+		let in_category = RatingHistoryOptions {
+			category: Some("Items".to_string()),
+			..liked
+		};
+		let history = db.get_rating_history(&in_category, 3).await.unwrap();
+		assert_eq!(vec!["1st", "nsfw-mod"], names(history));
+
+		let by_owner = RatingHistoryOptions {
+			category: None,
+			owner: Some("someone".to_string()),
+			..in_category
+		};
+		let history = db.get_rating_history(&by_owner, 3).await.unwrap();
+		let rated = &history.items[0];
+		assert_eq!(1, history.total);
+		assert_eq!("new-update", rated.modd.name);
+		assert_eq!(Rating::Like, rated.rating);
+		assert!(rated.rated_at.is_some());
 	}
 
-	#[sqlx::test]
-	async fn set_mod_import_date_multiple_times(pool: Pool<Postgres>) {
+	#[sqlx::test(fixtures("mods", "users", "user_ratings"))]
+	async fn recommended_order(pool: Pool<Postgres>) {
 		let db = Database { pool };
 
-		let old = OffsetDateTime::parse("2000-01-01T00:00:00.000000Z", &Iso8601::DEFAULT).unwrap();
-		let mid = OffsetDateTime::parse("2002-02-22T00:00:00.000000Z", &Iso8601::DEFAULT).unwrap();
-		let new = OffsetDateTime::parse("2025-03-03T03:03:03.000000Z", &Iso8601::DEFAULT).unwrap();
+		let options = ModQueryOptions {
+			community: DEFAULT_COMMUNITY.to_string(),
+			ignored_categories: Default::default(),
+			limit: 100,
+			include_deprecated: true,
+			include_nsfw: true,
+			order: QueueOrder::Recommended,
+		};
+		let names = |mods: Vec<Mod>| mods.into_iter().map(|m| m.name).collect::<Vec<_>>();
 
-		db.set_mods_imported_date(old).await.unwrap();
-		db.set_mods_imported_date(mid).await.unwrap();
-		db.set_mods_imported_date(new).await.unwrap();
+		// without ratings there's nothing to compare, the mods are in the usual order
+		let updated_order = ModQueryOptions {
+			limit: 100,
+			include_deprecated: true,
+			include_nsfw: true,
+			..Default::default()
+		};
+		let cold_start = db.get_mods(&options, 0).await.unwrap();
+		assert_eq!(
+			names(db.get_mods(&updated_order, 0).await.unwrap()),
+			names(cold_start)
+		);
 
-		let date = db.latest_mod_import_date().await.unwrap().unwrap();
-		assert_eq!(new, date);
+		// same ratings as the user with mixed ratings in the user ratings fixture, the users who
+		// liked or disliked everything agree with one rating and disagree with the other
+		let first = Uuid::parse_str("00000000-0000-0000-0000-000000000001").unwrap();
+		let no_category = Uuid::parse_str("00000000-0000-0000-0000-000000000008").unwrap();
+		db.insert_mod_rating(&first, &Rating::Like, 0)
+			.await
+			.unwrap();
+		db.insert_mod_rating(&no_category, &Rating::Dislike, 0)
+			.await
+			.unwrap();
+
+		let recommended = db.get_mods(&options, 0).await.unwrap();
+		let expected = vec![
+			// liked by the similar user
+			"new-update",
+			"nsfw-mod",
+			// not rated by the similar user
+			"dep-nsfw",
+			"5th",
+			"6th",
+			"nsfw-2",
+			// disliked by the similar user
+			"dep-mod",
+			"old-mod",
+		];
+		assert_eq!(expected, names(recommended));
 	}
 
-	#[sqlx::test]
-	async fn insert_and_query_categories(pool: Pool<Postgres>) {
+	#[sqlx::test(fixtures("mod_dependencies", "users"))]
+	async fn finding_mods_by_full_name(pool: Pool<Postgres>) {
 		let db = Database { pool };
-		let categories = hashset_of(vec!["Foo", "Bar", "Baz", "Cat", "Dog"]);
-		db.insert_categories(&categories).await.unwrap();
 
+		let api = Uuid::parse_str("00000000-0000-0000-0000-0000000000a2").unwrap();
+		let pack = Uuid::parse_str("00000000-0000-0000-0000-0000000000a1").unwrap();
+		db.insert_mod_rating(&api, &Rating::Dislike, 1)
+			.await
+			.unwrap();
+
+		let full_names = vec![
+			"Lib-Pack".to_string(),
+			"Lib-Api".to_string(),
+			"Not-Imported".to_string(),
+		];
 		let result = db
-			.get_categories()
+			.find_mods_by_full_name(&full_names, DEFAULT_COMMUNITY, 1)
 			.await
-			.unwrap()
-			.into_iter()
-			.map(|ctg| ctg.name)
-			.collect::<HashSet<_>>();
-		let expected = hashset_of(vec!["Foo", "Bar", "Baz", "Cat", "Dog"]);
+			.unwrap();
 
+		let expected = vec![
+			FullNameMatch {
+				full_name: "Lib-Api".to_string(),
+				id: api,
+				rating: Some(Rating::Dislike),
+			},
+			FullNameMatch {
+				full_name: "Lib-Pack".to_string(),
+				id: pack,
+				rating: None,
+			},
+		];
 		assert_eq!(expected, result);
-	}
 
-	#[sqlx::test(fixtures("users"))]
-	async fn inserting_and_querying_mods(pool: Pool<Postgres>) {
-		let null = "".to_string();
+		db.insert_mod_ratings(&[api, pack], &Rating::Like, 1)
+			.await
+			.unwrap();
+		// the existing rating is kept
+		assert_eq!(
+			Some(Rating::Dislike),
+			db.get_mod_rating(&api, 1).await.unwrap()
+		);
+		assert_eq!(
+			Some(Rating::Like),
+			db.get_mod_rating(&pack, 1).await.unwrap()
+		);
+	}
 
+	#[sqlx::test(fixtures("mods", "users", "user_ratings"))]
+	async fn delisted_mods_are_omitted_from_queries(pool: Pool<Postgres>) {
 		let db = Database { pool };
-		db.insert_categories(&hashset_of(vec!["first", "second", "third"]))
+
+		let listed = (1..=8)
+			.map(|i| Uuid::parse_str(&format!("00000000-0000-0000-0000-{i:012}")).unwrap())
+			.collect::<Vec<_>>();
+		let mut tx = db.begin_import(ImportProgress::default()).await.unwrap();
+		let delisted_count = tx
+			.update_delisted_mods(DEFAULT_COMMUNITY, &listed, OffsetDateTime::now_utc())
 			.await
 			.unwrap();
-		let categories = db.get_categories().await.unwrap();
+		tx.commit().await.unwrap();
+		assert_eq!(2, delisted_count);
 
-		let m1 = Mod {
-			name: "mod-1".to_string(),
-			owner: "cat".to_string(),
-			description: "first mod".to_string(),
-			icon_url: "icon-1 url".to_string(),
-			package_url: "package-1 url".to_string(),
-			id: Uuid::parse_str("aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa").unwrap(),
-			categories: vec![
-				"first".to_string(),
-				"second".to_string(),
-				"third".to_string(),
-			],
+		let query_options = ModQueryOptions {
+			community: DEFAULT_COMMUNITY.to_string(),
+			ignored_categories: Default::default(),
+			limit: 100,
+			include_deprecated: true,
+			include_nsfw: true,
+			order: QueueOrder::Updated,
 		};
-		let date_1 = Date::parse("2025-03-22T19:59:59.012345Z", &Iso8601::DEFAULT).unwrap();
+		let result = db.get_mods(&query_options, 0).await.unwrap();
+		let expected = hashset_of(vec![
+			"1st",
+			"dep-mod",
+			"nsfw-mod",
+			"dep-nsfw",
+			"5th",
+			"6th",
+			"nsfw-2",
+			"no-category",
+		]);
+		assert_eq!(expected, mod_names(result));
 
-		let m2 = Mod {
-			name: "mod-2".to_string(),
-			owner: "dog".to_string(),
-			description: "second mod".to_string(),
-			icon_url: "icon-2 url".to_string(),
-			package_url: "package-2 url".to_string(),
-			id: Uuid::parse_str("bbbbbbbb-bbbb-bbbb-bbbb-bbbbbbbbbbbb").unwrap(),
-			categories: Default::default(),
-		};
-		let date_2 = Date::parse("2025-03-22T22:22:22.222222Z", &Iso8601::DEFAULT).unwrap();
+		// user ratings fixture has an user with this id who has liked "new-update"
+		let liked = db
+			.get_rated_mods(&Rating::Like, DEFAULT_COMMUNITY, 100, 3)
+			.await
+			.unwrap();
+		let delisted = liked
+			.into_iter()
+			.filter(|m| m.delisted)
+			.map(|m| m.name)
+			.collect::<Vec<_>>();
+		assert_eq!(vec!["new-update"], delisted);
+	}
 
-		let mods = vec![
-			InsertMod {
-				uuid4: m1.id.clone(),
-				name: &m1.name,
-				description: &m1.description,
-				icon_url: &m1.icon_url,
-				owner: &m1.owner,
-				package_url: &m1.package_url,
-				full_name: &null,
-				updated_date: date_1,
-				rating: 12345,
-				is_deprecated: false,
-				has_nsfw_content: false,
-				category_ids: HashSet::from_iter(vec![
-					&categories.get(0).unwrap().id,
-					&categories.get(1).unwrap().id,
-					&categories.get(2).unwrap().id,
-				]),
-			},
-			InsertMod {
-				uuid4: m2.id.clone(),
-				name: &m2.name,
-				description: &m2.description,
-				icon_url: &m2.icon_url,
-				owner: &m2.owner,
-				package_url: &m2.package_url,
-				full_name: &null,
-				updated_date: date_2,
-				rating: 54321,
-				is_deprecated: true,
-				has_nsfw_content: true,
-				category_ids: HashSet::from_iter(vec![]),
-			},
-		];
+	#[sqlx::test(fixtures("mods", "users"))]
+	async fn relisted_mods_are_no_longer_delisted(pool: Pool<Postgres>) {
+		let db = Database { pool };
+
+		let all = (1..=10)
+			.map(|i| Uuid::parse_str(&format!("00000000-0000-0000-0000-{i:012}")).unwrap())
+			.collect::<Vec<_>>();
 
-		db.insert_mods(&mods, 150).await.unwrap();
+		let now = OffsetDateTime::now_utc();
+		let mut tx = db.begin_import(ImportProgress::default()).await.unwrap();
+		tx.update_delisted_mods(DEFAULT_COMMUNITY, &all[..5], now)
+			.await
+			.unwrap();
+		let delisted_count = tx
+			.update_delisted_mods(DEFAULT_COMMUNITY, &all, now)
+			.await
+			.unwrap();
+		tx.commit().await.unwrap();
+		assert_eq!(0, delisted_count);
 
 		let query_options = ModQueryOptions {
+			community: DEFAULT_COMMUNITY.to_string(),
 			ignored_categories: Default::default(),
 			limit: 100,
 			include_deprecated: true,
 			include_nsfw: true,
+			order: QueueOrder::Updated,
 		};
+		let result = db.get_mods(&query_options, 0).await.unwrap();
+		assert_eq!(10, result.len());
+	}
 
-		let mut result = db.get_mods(&query_options, 0).await.unwrap();
-		result.sort_by(|a, b| a.name.cmp(&b.name));
+	#[sqlx::test(fixtures("mods", "communities"))]
+	async fn querying_mods_of_community(pool: Pool<Postgres>) {
+		let db = Database { pool };
 
-		let mut expected = vec![m1, m2];
-		expected.sort_by(|a, b| a.name.cmp(&b.name));
+		let query_options = ModQueryOptions {
+			community: "content-warning".to_string(),
+			ignored_categories: Default::default(),
+			limit: 100,
+			include_deprecated: true,
+			include_nsfw: true,
+			order: QueueOrder::Updated,
+		};
+		let result = db.get_mods(&query_options, 0).await.unwrap();
 
-		// categories might be returned in random order and FromRow doesn't support HashSet
-		// -> sort them to prevent tests from failing randomly
-		result.iter_mut().for_each(|modd| modd.categories.sort());
-		expected.iter_mut().for_each(|modd| modd.categories.sort());
-		assert_eq!(expected, result);
+		assert_eq!(hashset_of(vec!["Pack", "Era"]), mod_names(result));
 	}
 
-	#[sqlx::test(fixtures("mods", "users", "user_ratings"))]
-	async fn rated_mods_are_omitted_from_queries(pool: Pool<Postgres>) {
+	#[sqlx::test(fixtures("mods", "communities"))]
+	async fn delisting_only_affects_community(pool: Pool<Postgres>) {
 		let db = Database { pool };
 
-		db.insert_mod_rating(
-			&Uuid::parse_str("00000000-0000-0000-0000-000000000005").unwrap(),
-			&Rating::Like,
-			0,
-		)
-		.await
-		.unwrap();
-		db.insert_mod_rating(
-			&Uuid::parse_str("00000000-0000-0000-0000-000000000006").unwrap(),
-			&Rating::Dislike,
-			0,
-		)
-		.await
-		.unwrap();
+		let pack = Uuid::parse_str("00000000-0000-0000-0000-0000000000b1").unwrap();
+		let mut tx = db.begin_import(ImportProgress::default()).await.unwrap();
+		let delisted_count = tx
+			.update_delisted_mods("content-warning", &[pack], OffsetDateTime::now_utc())
+			.await
+			.unwrap();
+		tx.commit().await.unwrap();
+		assert_eq!(1, delisted_count);
 
 		let query_options = ModQueryOptions {
+			community: DEFAULT_COMMUNITY.to_string(),
 			ignored_categories: Default::default(),
 			limit: 100,
 			include_deprecated: true,
 			include_nsfw: true,
+			order: QueueOrder::Updated,
 		};
-
 		let result = db.get_mods(&query_options, 0).await.unwrap();
-
-		let mods = mod_names(result);
-		let expected = hashset_of(vec![
-			"1st",
-			"dep-mod",
-			"nsfw-mod",
-			"dep-nsfw",
-			"nsfw-2",
-			"no-category",
-			"new-update",
-			"old-mod",
-		]);
-
-		assert_eq!(expected, mods);
+		assert_eq!(10, result.len());
 	}
 
-	#[sqlx::test(fixtures("mods", "users", "user_ratings"))]
-	async fn querying_rated_mods(pool: Pool<Postgres>) {
+	#[sqlx::test]
+	async fn communities_have_separate_categories(pool: Pool<Postgres>) {
 		let db = Database { pool };
 
-		// user ratings fixture has an user with this id who has liked and disliked some mods
-		let user_with_ratings_id = 3;
-		let result = db
-			.get_rated_mods(&Rating::Like, 100, user_with_ratings_id)
+		let mut tx = db.begin_import(ImportProgress::default()).await.unwrap();
+		tx.insert_categories(DEFAULT_COMMUNITY, &hashset_of(vec!["Misc", "Suits"]))
+			.await
+			.unwrap();
+		tx.insert_categories("content-warning", &hashset_of(vec!["Misc"]))
 			.await
 			.unwrap();
+		tx.commit().await.unwrap();
 
-		let mods = mod_names(result);
-		let expected = hashset_of(vec!["1st", "nsfw-mod", "new-update"]);
+		let lethal_company = db.get_categories(DEFAULT_COMMUNITY).await.unwrap();
+		let content_warning = db.get_categories("content-warning").await.unwrap();
 
-		assert_eq!(expected, mods);
+		assert_eq!(2, lethal_company.len());
+		assert_eq!(1, content_warning.len());
+		assert!(!lethal_company.contains(&content_warning[0]));
 	}
 
 	#[sqlx::test]
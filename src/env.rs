@@ -1,8 +1,12 @@
-use std::{collections::HashMap, env, str::FromStr, time::Duration};
+use std::{collections::HashMap, env, path::PathBuf, str::FromStr, time::Duration};
 
 use log::LevelFilter;
 
-use crate::mods::ModRefreshOptions;
+use time::OffsetDateTime;
+
+use crate::mods::{
+	CronSchedule, DEFAULT_COMMUNITY, ImportSchedule, ModRefreshOptions, parse_snapshot_name,
+};
 
 #[derive(Clone)]
 pub struct Env {
@@ -11,6 +15,22 @@ pub struct Env {
 	pub sql_chunk_size: usize,
 	pub mod_refresh_options: ModRefreshOptions,
 	pub db_url: String,
+	/// Thunderstore communities to import mods from, the first one is the default for new users
+	pub communities: Vec<String>,
+	/// Thunderstore instance the package lists are downloaded from
+	pub thunderstore_url: String,
+	/// directory of the downloaded package lists
+	pub cache_dir: PathBuf,
+	/// how many times a failed package list download is retried
+	pub download_retries: u32,
+	/// delay before the first retry, doubled for each retry after that
+	pub download_retry_delay: Duration,
+	/// how many compressed snapshots of each package list are kept
+	pub cache_snapshots: usize,
+	/// in cache-only mode import the newest snapshot taken at or before this time instead of the cache
+	pub cache_snapshot: Option<String>,
+	/// largest mod icon stored in the icon cache in bytes, 0 if icons aren't cached
+	pub icon_max_size: usize,
 }
 
 impl Env {
@@ -24,6 +44,14 @@ impl Env {
 			sql_chunk_size: chunk_size(&vars),
 			mod_refresh_options: mod_refresh_options(&vars),
 			db_url: db_url(&vars),
+			communities: communities(&vars),
+			thunderstore_url: thunderstore_url(&vars),
+			cache_dir: cache_dir(&vars),
+			download_retries: download_retries(&vars),
+			download_retry_delay: download_retry_delay(&vars),
+			cache_snapshots: cache_snapshots(&vars),
+			cache_snapshot: cache_snapshot(&vars),
+			icon_max_size: icon_max_size(&vars),
 		}
 	}
 }
@@ -33,7 +61,7 @@ fn port(vars: &HashMap<String, String>) -> u16 {
 
 	port_str
 		.parse()
-		.expect(&format!("Can't convert PORT to number: '{port_str}'"))
+		.unwrap_or_else(|_| panic!("Can't convert PORT to number: '{port_str}'"))
 }
 
 fn log_level(vars: &HashMap<String, String>) -> LevelFilter {
@@ -41,7 +69,7 @@ fn log_level(vars: &HashMap<String, String>) -> LevelFilter {
 		.get("LOG_LEVEL")
 		.expect("Missing .env variable: LOG_LEVEL");
 
-	if let Some(log_level) = LevelFilter::from_str(&log_level).ok() {
+	if let Ok(log_level) = LevelFilter::from_str(log_level) {
 		return log_level;
 	}
 
@@ -63,7 +91,7 @@ fn chunk_size(vars: &HashMap<String, String>) -> usize {
 
 	let sql_chunk_size = str
 		.parse()
-		.expect(&format!("Can't convert SQL_CHUNK_SIZE to number: '{str}'"));
+		.unwrap_or_else(|_| panic!("Can't convert SQL_CHUNK_SIZE to number: '{str}'"));
 
 	if sql_chunk_size == 0 {
 		panic!("SQL_CHUNK_SIZE can't be zero");
@@ -78,32 +106,161 @@ fn mod_refresh_options(vars: &HashMap<String, String>) -> ModRefreshOptions {
 		.expect("Missing .env variable: MOD_REFRESH")
 		.as_str();
 
-	let err_msg_missing_interval = "Missing .env variable: MOD_IMPORT_INTERVAL_HOURS";
-	let import_interval_secs = vars
-		.get("MOD_IMPORT_INTERVAL_HOURS")
-		.map(|str| {
-			str.parse::<u64>().expect(&format!(
-				"MOD_IMPORT_INTERVAL_HOURS is not a valid number: '{str}'"
-			))
-		})
-		.map(|hours| hours * 60 * 60);
+	let schedule = || match vars
+		.get("MOD_IMPORT_SCHEDULE")
+		.filter(|str| !str.is_empty())
+	{
+		Some(expression) => ImportSchedule::Cron(import_schedule(vars, expression)),
+		None => ImportSchedule::Interval(import_interval(vars)),
+	};
 
 	match str {
 		"none" => ModRefreshOptions::NoRefresh,
-		"cache-only" => ModRefreshOptions::CacheOnly(Duration::from_secs(
-			import_interval_secs.expect(err_msg_missing_interval),
-		)),
-		"expiration" => ModRefreshOptions::DownloadIfExpired(Duration::from_secs(
-			import_interval_secs.expect(err_msg_missing_interval),
-		)),
+		"cache-only" => ModRefreshOptions::CacheOnly(schedule()),
+		"expiration" => ModRefreshOptions::DownloadIfExpired(schedule()),
 		_ => panic!(
 			"Not a valid mod refresh option: '{str}'. Allowed values are: expiration, cache-only, none"
 		),
 	}
 }
 
+fn import_interval(vars: &HashMap<String, String>) -> Duration {
+	let str = vars
+		.get("MOD_IMPORT_INTERVAL_HOURS")
+		.expect("Missing .env variable: MOD_IMPORT_INTERVAL_HOURS");
+
+	let hours = str
+		.parse::<u64>()
+		.unwrap_or_else(|_| panic!("MOD_IMPORT_INTERVAL_HOURS is not a valid number: '{str}'"));
+
+	Duration::from_secs(hours * 60 * 60)
+}
+
+fn import_schedule(vars: &HashMap<String, String>, expression: &str) -> CronSchedule {
+	let timezone = vars
+		.get("MOD_IMPORT_TIMEZONE")
+		.map(String::as_str)
+		.filter(|str| !str.is_empty())
+		.unwrap_or("UTC");
+
+	let schedule = CronSchedule::parse(expression, timezone)
+		.unwrap_or_else(|err| panic!("MOD_IMPORT_SCHEDULE is not valid: {err}"));
+
+	if schedule.next_after(OffsetDateTime::now_utc()).is_none() {
+		panic!("MOD_IMPORT_SCHEDULE never runs: '{expression}'");
+	}
+
+	schedule
+}
+
 fn db_url(vars: &HashMap<String, String>) -> String {
 	vars.get("DB_URL")
 		.expect("Missing .env variable: DB_URL")
 		.clone()
 }
+
+fn communities(vars: &HashMap<String, String>) -> Vec<String> {
+	let Some(str) = vars.get("COMMUNITIES") else {
+		return vec![DEFAULT_COMMUNITY.to_string()];
+	};
+
+	let communities = str
+		.split(',')
+		.map(|community| community.trim().to_string())
+		.filter(|community| !community.is_empty())
+		.collect::<Vec<_>>();
+
+	if communities.is_empty() {
+		panic!("COMMUNITIES can't be empty");
+	}
+
+	if let Some(community) = communities.iter().find(|community| {
+		!community
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || c == '-')
+	}) {
+		panic!("Not a valid community slug: '{community}'");
+	}
+
+	communities
+}
+
+fn thunderstore_url(vars: &HashMap<String, String>) -> String {
+	let url = vars
+		.get("THUNDERSTORE_URL")
+		.filter(|url| !url.is_empty())
+		.map(|url| url.trim_end_matches('/'))
+		.unwrap_or("https://thunderstore.io");
+
+	if !url.starts_with("http://") && !url.starts_with("https://") {
+		panic!("Not a valid THUNDERSTORE_URL: '{url}'");
+	}
+
+	url.to_string()
+}
+
+fn cache_dir(vars: &HashMap<String, String>) -> PathBuf {
+	vars.get("CACHE_DIR")
+		.filter(|dir| !dir.is_empty())
+		.map(PathBuf::from)
+		.unwrap_or_else(|| PathBuf::from("data"))
+}
+
+fn download_retries(vars: &HashMap<String, String>) -> u32 {
+	vars.get("DOWNLOAD_RETRIES")
+		.filter(|str| !str.is_empty())
+		.map(|str| {
+			str.parse()
+				.unwrap_or_else(|_| panic!("Can't convert DOWNLOAD_RETRIES to number: '{str}'"))
+		})
+		.unwrap_or(3)
+}
+
+fn download_retry_delay(vars: &HashMap<String, String>) -> Duration {
+	let secs = vars
+		.get("DOWNLOAD_RETRY_DELAY_SECS")
+		.filter(|str| !str.is_empty())
+		.map(|str| {
+			str.parse().unwrap_or_else(|_| {
+				panic!("Can't convert DOWNLOAD_RETRY_DELAY_SECS to number: '{str}'")
+			})
+		})
+		.unwrap_or(10);
+
+	Duration::from_secs(secs)
+}
+
+fn cache_snapshots(vars: &HashMap<String, String>) -> usize {
+	vars.get("CACHE_SNAPSHOTS")
+		.filter(|str| !str.is_empty())
+		.map(|str| {
+			str.parse()
+				.unwrap_or_else(|_| panic!("Can't convert CACHE_SNAPSHOTS to number: '{str}'"))
+		})
+		.unwrap_or(5)
+}
+
+fn cache_snapshot(vars: &HashMap<String, String>) -> Option<String> {
+	let snapshot = vars.get("CACHE_SNAPSHOT").filter(|str| !str.is_empty())?;
+
+	if parse_snapshot_name(snapshot).is_none() {
+		panic!(
+			"Not a valid CACHE_SNAPSHOT: '{snapshot}'. Expected a time such as 20250320T100000Z"
+		);
+	}
+
+	Some(snapshot.clone())
+}
+
+fn icon_max_size(vars: &HashMap<String, String>) -> usize {
+	let kilobytes: usize = vars
+		.get("ICON_MAX_SIZE_KB")
+		.filter(|str| !str.is_empty())
+		.map(|str| {
+			str.parse()
+				.unwrap_or_else(|_| panic!("Can't convert ICON_MAX_SIZE_KB to number: '{str}'"))
+		})
+		.unwrap_or(256);
+
+	kilobytes * 1024
+}
@@ -0,0 +1,15 @@
+INSERT INTO categories(id, name, community) VALUES
+(100, 'Misc', 'content-warning');
+
+INSERT INTO mods
+(id,                                      name,       full_name,  community,         updated_date,                  date_created,                  deprecated, nsfw,  description, icon_url, owner,  package_url,    rating) VALUES
+('00000000-0000-0000-0000-0000000000b1',  'Pack',     'Lib-Pack', 'content-warning', '2025-03-20T10:00:00.000000Z', '2025-03-20T10:00:00.000000Z', false,      false, '',          '',       'Lib',  'cw-pack-url',  0),
+('00000000-0000-0000-0000-0000000000b2',  'Era',      'Cam-Era',  'content-warning', '2025-03-20T10:00:00.000000Z', '2025-03-20T10:00:00.000000Z', false,      false, '',          '',       'Cam',  'cw-era-url',   0);
+
+INSERT INTO mod_category(category_id, mod_id) VALUES
+(100, '00000000-0000-0000-0000-0000000000b2');
+
+-- Lib-Pack exists in both communities, Lib-Api only in lethal-company
+INSERT INTO mod_dependencies(mod_id, dependency_full_name, dependency_version) VALUES
+('00000000-0000-0000-0000-0000000000b2', 'Lib-Pack', '1.0.0'),
+('00000000-0000-0000-0000-0000000000b2', 'Lib-Api',  '1.0.0');
@@ -0,0 +1,19 @@
+INSERT INTO mods
+(id,                                      name,       full_name,           updated_date,                  date_created,                  deprecated, nsfw,  description, icon_url, owner,  package_url,   rating) VALUES
+('00000000-0000-0000-0000-0000000000a1',  'Pack',     'Lib-Pack',          '2025-03-20T10:00:00.000000Z', '2025-03-20T10:00:00.000000Z', false,      false, '',          '',       'Lib',  'pack-url',    0),
+('00000000-0000-0000-0000-0000000000a2',  'Api',      'Lib-Api',           '2025-03-20T10:00:00.000000Z', '2025-03-20T10:00:00.000000Z', false,      false, '',          '',       'Lib',  'api-url',     0),
+('00000000-0000-0000-0000-0000000000a3',  'Suits',    'Cat-Suits',         '2025-03-20T10:00:00.000000Z', '2025-03-20T10:00:00.000000Z', false,      false, '',          '',       'Cat',  'suits-url',   0),
+('00000000-0000-0000-0000-0000000000a4',  'Music',    'Dog-Music',         '2025-03-20T10:00:00.000000Z', '2025-03-20T10:00:00.000000Z', false,      false, '',          '',       'Dog',  'music-url',   0),
+('00000000-0000-0000-0000-0000000000a5',  'Cycle',    'Dog-Cycle',         '2025-03-20T10:00:00.000000Z', '2025-03-20T10:00:00.000000Z', false,      false, '',          '',       'Dog',  'cycle-url',   0),
+('00000000-0000-0000-0000-0000000000a6',  'Loner',    'Dog-Loner',         '2025-03-20T10:00:00.000000Z', '2025-03-20T10:00:00.000000Z', false,      false, '',          '',       'Dog',  'loner-url',   0);
+
+-- Cat-Suits -> Lib-Api -> Lib-Pack
+-- Dog-Music -> Lib-Pack, Gone-Missing (not imported)
+-- Dog-Cycle <-> Dog-Music
+INSERT INTO mod_dependencies(mod_id, dependency_full_name, dependency_version) VALUES
+('00000000-0000-0000-0000-0000000000a2', 'Lib-Pack',     '5.4.2100'),
+('00000000-0000-0000-0000-0000000000a3', 'Lib-Api',      '1.0.0'),
+('00000000-0000-0000-0000-0000000000a4', 'Lib-Pack',     '5.4.2100'),
+('00000000-0000-0000-0000-0000000000a4', 'Gone-Missing', '0.0.1'),
+('00000000-0000-0000-0000-0000000000a4', 'Dog-Cycle',    '1.0.0'),
+('00000000-0000-0000-0000-0000000000a5', 'Dog-Music',    '1.0.0');
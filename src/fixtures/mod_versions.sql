@@ -0,0 +1,8 @@
+INSERT INTO mod_versions
+(id,                                      mod_id,                                  version_number, date_created,                  description, downloads, file_size, download_url, is_active) VALUES
+('00000000-0000-0000-0000-0000000000b1',  '00000000-0000-0000-0000-0000000000a1',  '5.4.2000',     '2024-01-01T10:00:00.000000Z', '',          0,         0,         '',           false),
+('00000000-0000-0000-0000-0000000000b2',  '00000000-0000-0000-0000-0000000000a1',  '5.4.2100',     '2025-01-01T10:00:00.000000Z', '',          0,         0,         '',           true),
+('00000000-0000-0000-0000-0000000000b3',  '00000000-0000-0000-0000-0000000000a2',  '1.0.0',        '2025-01-01T10:00:00.000000Z', '',          0,         0,         '',           true),
+('00000000-0000-0000-0000-0000000000b4',  '00000000-0000-0000-0000-0000000000a3',  '2.0.0',        '2025-01-01T10:00:00.000000Z', '',          0,         0,         '',           true),
+('00000000-0000-0000-0000-0000000000b5',  '00000000-0000-0000-0000-0000000000a4',  '1.0.0',        '2025-01-01T10:00:00.000000Z', '',          0,         0,         '',           true),
+('00000000-0000-0000-0000-0000000000b6',  '00000000-0000-0000-0000-0000000000a5',  '1.0.0',        '2025-01-01T10:00:00.000000Z', '',          0,         0,         '',           true);
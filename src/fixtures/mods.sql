@@ -6,17 +6,17 @@ INSERT INTO categories(id, name) VALUES
 (4, 'Misc');
 
 INSERT INTO mods
-(id,                                      name,          updated_date,                  deprecated, nsfw,  description, icon_url, full_name, owner, package_url, rating) VALUES
-('00000000-0000-0000-0000-000000000001',  '1st',         '2025-03-20T10:00:00.000000Z', false,      false, '',          '',       '',        '',    '',          0),
-('00000000-0000-0000-0000-000000000002',  'dep-mod',     '2025-03-20T09:00:00.000000Z', true,       false, '',          '',       '',        '',    '',          0),
-('00000000-0000-0000-0000-000000000003',  'nsfw-mod',    '2025-03-20T08:00:00.000000Z', false,      true,  '',          '',       '',        '',    '',          0),
-('00000000-0000-0000-0000-000000000004',  'dep-nsfw',    '2025-03-20T07:00:00.000000Z', true,       true,  '',          '',       '',        '',    '',          0),
-('00000000-0000-0000-0000-000000000005',  '5th',         '2025-03-09T00:00:00.000000Z', false,      false, '',          '',       '',        '',    '',          0),
-('00000000-0000-0000-0000-000000000006',  '6th',         '2025-03-08T00:00:00.000000Z', false,      false, '',          '',       '',        '',    '',          0),
-('00000000-0000-0000-0000-000000000007',  'nsfw-2',      '2025-03-07T00:00:00.000000Z', false,      true,  '',          '',       '',        '',    '',          0),
-('00000000-0000-0000-0000-000000000008',  'no-category', '2025-03-06T00:00:00.000000Z', false,      false, '',          '',       '',        '',    '',          0),
-('00000000-0000-0000-0000-000000000009',  'new-update',  '2025-03-21T00:00:00.000000Z', false,      false, '',          '',       '',        '',    '',          0),
-('00000000-0000-0000-0000-000000000010',  'old-mod',     '2020-01-01T00:00:00.000000Z', false,      false, '',          '',       '',        '',    '',          0);
+(id,                                      name,          updated_date,                  date_created,                  deprecated, nsfw,  description, icon_url, full_name, owner, package_url, rating) VALUES
+('00000000-0000-0000-0000-000000000001',  '1st',         '2025-03-20T10:00:00.000000Z', '2025-03-20T10:00:00.000000Z', false,      false, '',          '',       '',        '',    '',          0),
+('00000000-0000-0000-0000-000000000002',  'dep-mod',     '2025-03-20T09:00:00.000000Z', '2025-03-20T09:00:00.000000Z', true,       false, '',          '',       '',        '',    '',          0),
+('00000000-0000-0000-0000-000000000003',  'nsfw-mod',    '2025-03-20T08:00:00.000000Z', '2025-03-20T08:00:00.000000Z', false,      true,  '',          '',       '',        '',    '',          0),
+('00000000-0000-0000-0000-000000000004',  'dep-nsfw',    '2025-03-20T07:00:00.000000Z', '2025-03-20T07:00:00.000000Z', true,       true,  '',          '',       '',        '',    '',          0),
+('00000000-0000-0000-0000-000000000005',  '5th',         '2025-03-09T00:00:00.000000Z', '2025-03-09T00:00:00.000000Z', false,      false, '',          '',       '',        '',    '',          0),
+('00000000-0000-0000-0000-000000000006',  '6th',         '2025-03-08T00:00:00.000000Z', '2025-03-08T00:00:00.000000Z', false,      false, '',          '',       '',        '',    '',          0),
+('00000000-0000-0000-0000-000000000007',  'nsfw-2',      '2025-03-07T00:00:00.000000Z', '2025-03-07T00:00:00.000000Z', false,      true,  '',          '',       '',        '',    '',          0),
+('00000000-0000-0000-0000-000000000008',  'no-category', '2025-03-06T00:00:00.000000Z', '2025-03-06T00:00:00.000000Z', false,      false, '',          '',       '',        '',    '',          0),
+('00000000-0000-0000-0000-000000000009',  'new-update',  '2025-03-21T00:00:00.000000Z', '2025-03-21T00:00:00.000000Z', false,      false, '',          '',       '',        '',    '',          0),
+('00000000-0000-0000-0000-000000000010',  'old-mod',     '2020-01-01T00:00:00.000000Z', '2020-01-01T00:00:00.000000Z', false,      false, '',          '',       '',        '',    '',          0);
 
 INSERT INTO mod_category(category_id, mod_id) VALUES
 (1, '00000000-0000-0000-0000-000000000005'),
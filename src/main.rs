@@ -1,18 +1,35 @@
 use std::{sync::Mutex, time::Duration};
 
+use actix_multipart::form::{MultipartFormConfig, tempfile::TempFileConfig};
 use actix_web::{
-	App, HttpServer, middleware,
+	App, HttpServer, guard, middleware,
 	web::{self, Data},
 };
 use db::Database;
 use env::Env;
-use mods::{are_mods_expired, do_import_mods, import_mods_if_expired};
+use middlewares::{PrivilegeValidator, TokenValidator};
+use mods::{
+	ImportTrigger, are_mods_expired, do_import_mods, import_mods_if_expired, import_upload,
+	move_legacy_cache, next_import_time, rollback_to_snapshot,
+};
 use serde_qs::actix::QsQueryConfig;
 use services::{
-	css, default_handler, favicon, home_page,
-	import_mods::{ImportStatus, import_mods, import_mods_page},
+	css, default_handler, favicon,
+	history::rating_history_page,
+	home_page,
+	icons::mod_icon,
+	import_mods::{
+		ImportRequest, ImportStatus, PACKAGE_LIST_UPLOAD_LIMIT, cancel_import, import_history_page,
+		import_mods, import_mods_page, import_problems_page, import_progress,
+		import_snapshots_page, import_upload_page, rollback_mods, upload_package_list,
+	},
 	login_error_page,
-	ratings::{post_rating, rated_mods, rating_page},
+	mod_details::mod_details_page,
+	ratings::{
+		change_rating, clear_rating, disliked_mods, export_liked_mods, import_profile,
+		import_profile_page, post_rating, rated_mods, rating_page, undo_rating,
+	},
+	search::search_page,
 	settings::{save_settings, settings_page},
 	users::{basic_auth, create_user, create_user_page, login_page, logout, logout_page},
 };
@@ -22,6 +39,7 @@ mod db;
 mod env;
 mod middlewares;
 mod mods;
+mod profile;
 mod services;
 
 #[actix_web::main]
@@ -30,7 +48,16 @@ async fn main() -> std::io::Result<()> {
 	env_logger::builder().filter_level(env.log_level).init();
 
 	let db = Database::open_connection(&env.db_url, 5).await.unwrap();
-	import_mods_if_expired(&db, &env).await.unwrap();
+	db.fail_interrupted_import_runs().await.unwrap();
+	move_legacy_cache(&env)
+		.inspect_err(|error| log::error!("Failed to move the old package list cache: {error}"))
+		.ok();
+	// uploaded package lists are written here
+	std::fs::create_dir_all(&env.cache_dir)?;
+	import_mods_if_expired(&db, &env)
+		.await
+		.inspect_err(|error| log::error!("Failed to import mods on startup: {error}"))
+		.ok();
 
 	let tera = Data::new(Mutex::new(Tera::new("templates/*.html").unwrap()));
 
@@ -71,10 +98,14 @@ async fn main() -> std::io::Result<()> {
 
 	HttpServer::new(move || {
 		let qs_config = QsQueryConfig::default().qs_config(serde_qs::Config::new(5, false));
+		let upload_config = MultipartFormConfig::default().total_limit(PACKAGE_LIST_UPLOAD_LIMIT);
+		// in the cache directory, so the upload can be moved into the cache instead of copied
+		let upload_file_config = TempFileConfig::default().directory(&env.cache_dir);
 
 		App::new()
 			.wrap(middleware::Logger::default())
 			.app_data(Data::new(db.clone()))
+			.app_data(Data::new(env.clone()))
 			.app_data(tera.clone())
 			.app_data(qs_config)
 			.app_data(import_status.clone())
@@ -85,14 +116,41 @@ async fn main() -> std::io::Result<()> {
 			.service(login_page)
 			.service(login_error_page)
 			.service(css)
+			.service(mod_icon)
 			.service(import_mods_page)
 			.service(import_mods)
+			.service(import_progress)
+			.service(cancel_import)
+			.service(import_history_page)
+			.service(import_problems_page)
+			.service(import_snapshots_page)
+			.service(rollback_mods)
+			.service(import_upload_page)
+			.service(
+				web::resource("/import-upload")
+					.guard(guard::Post())
+					.app_data(upload_config)
+					.app_data(upload_file_config)
+					.wrap(PrivilegeValidator)
+					.wrap(TokenValidator)
+					.route(web::post().to(upload_package_list)),
+			)
 			.service(logout)
 			.service(logout_page)
 			.service(home_page)
 			.service(rating_page)
 			.service(post_rating)
+			.service(change_rating)
+			.service(clear_rating)
+			.service(undo_rating)
 			.service(rated_mods)
+			.service(disliked_mods)
+			.service(rating_history_page)
+			.service(search_page)
+			.service(mod_details_page)
+			.service(export_liked_mods)
+			.service(import_profile_page)
+			.service(import_profile)
 			.service(settings_page)
 			.service(save_settings)
 			.default_service(web::to(default_handler))
@@ -107,38 +165,37 @@ async fn import_request_checker(import_status: Data<Mutex<ImportStatus>>, db: Da
 	loop {
 		interval.tick().await;
 
-		let do_import = {
-			let status = import_status.lock().unwrap();
-			status.import_requested && !status.import_in_progress
-		};
-
-		if !do_import {
+		let Some((request, progress)) = import_status.lock().unwrap().start_requested() else {
 			continue;
-		}
-
-		{
-			let mut status = import_status.lock().unwrap();
-			status.import_in_progress = true;
-		}
+		};
 
-		do_import_mods(&db, &env).await.unwrap();
+		let result = match request {
+			ImportRequest::Refresh(trigger) => do_import_mods(&db, &env, trigger, &progress).await,
+			ImportRequest::Rollback {
+				community,
+				snapshot,
+			} => rollback_to_snapshot(&db, &env, &community, &snapshot, &progress).await,
+			ImportRequest::Upload { community } => {
+				import_upload(&db, &env, &community, &progress).await
+			}
+		};
+		result
+			.inspect_err(|error| log::error!("Failed to import mods: {error}"))
+			.ok();
 
-		{
-			let mut status = import_status.lock().unwrap();
-			status.import_in_progress = false;
-			status.import_requested = false;
-		}
+		import_status.lock().unwrap().finish();
 	}
 }
 
 async fn expiration_checker(import_status: Data<Mutex<ImportStatus>>, db: Database, env: Env) {
-	let mut interval = actix_rt::time::interval(Duration::from_secs(60 * 60));
+	let mut delay = Duration::ZERO;
 	loop {
-		interval.tick().await;
+		actix_rt::time::sleep(delay).await;
+		delay = next_check_delay(&db, &env).await;
 
 		let already_importing = {
 			let status = import_status.lock().unwrap();
-			status.import_in_progress || status.import_requested
+			status.import_in_progress || status.import_requested.is_some()
 		};
 
 		if already_importing {
@@ -152,7 +209,32 @@ async fn expiration_checker(import_status: Data<Mutex<ImportStatus>>, db: Databa
 
 		if need_to_import {
 			log::info!("Mods are expired, requesting reimport");
-			import_status.lock().unwrap().import_requested = true;
+			import_status.lock().unwrap().import_requested =
+				Some(ImportRequest::Refresh(ImportTrigger::Expiration));
 		}
 	}
 }
+
+/// Time until the mods should be checked again: when the next import is due, but at least a
+/// minute and at most an hour from now.
+async fn next_check_delay(db: &Database, env: &Env) -> Duration {
+	const MIN_DELAY: Duration = Duration::from_secs(60);
+	const MAX_DELAY: Duration = Duration::from_secs(60 * 60);
+
+	let next_import = next_import_time(db, env)
+		.await
+		.inspect_err(|error| log::error!("Failed to get next import time: {error}"))
+		.ok()
+		.flatten();
+
+	let Some(next_import) = next_import else {
+		return MAX_DELAY;
+	};
+
+	// the import is due only once its time has passed
+	let until_next = next_import - time::OffsetDateTime::now_utc() + time::Duration::SECOND;
+
+	Duration::try_from(until_next)
+		.unwrap_or(MIN_DELAY)
+		.clamp(MIN_DELAY, MAX_DELAY)
+}
@@ -56,19 +56,19 @@ where
 				actix_web::error::ErrorInternalServerError("Server error (can't find db)")
 			})?;
 
-			let user = {
+			let user_id = {
 				let ext = req.extensions();
 				let token_claims = ext.get::<TokenClaims>().ok_or_else(|| {
 					actix_web::error::ErrorInternalServerError("Server error (can't find token)")
 				})?;
-
-				let user = db
-					.find_user_by_id(token_claims.id)
-					.await?
-					.ok_or_else(|| actix_web::error::ErrorUnauthorized("Unauthorized"))?;
-				user
+				token_claims.id
 			};
 
+			let user = db
+				.find_user_by_id(user_id)
+				.await?
+				.ok_or_else(|| actix_web::error::ErrorUnauthorized("Unauthorized"))?;
+
 			if !user.has_import_privileges {
 				return Err(actix_web::error::ErrorUnauthorized(
 					"You don't have permission to use this",
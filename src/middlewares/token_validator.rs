@@ -54,15 +54,12 @@ where
 		let jwt_secret = std::env::var("JWT_SECRET").expect("JWT_SECRET is not set");
 		let key: Hmac<Sha256> = Hmac::new_from_slice(jwt_secret.as_bytes()).unwrap();
 
-		let token_claims = req
-			.cookie("lcmt-login")
-			.map(|cookie| {
-				let token_string = cookie.value();
-				let claims: Result<TokenClaims, _> = token_string.verify_with_key(&key);
+		let token_claims = req.cookie("lcmt-login").and_then(|cookie| {
+			let token_string = cookie.value();
+			let claims: Result<TokenClaims, _> = token_string.verify_with_key(&key);
 
-				claims.ok()
-			})
-			.flatten();
+			claims.ok()
+		});
 
 		match token_claims {
 			Some(value) => {
@@ -1,28 +1,45 @@
 use std::{
 	collections::{HashMap, HashSet},
 	error::Error,
-	fmt::Display,
-	path::Path,
-	string::FromUtf8Error,
-	time::Duration,
+	fmt::{self, Display},
+	io::Read,
 };
 
-use async_curl::{Actor, CurlActor};
-use curl::easy::{Easy2, Handler, WriteError};
-use serde::{Deserialize, Serialize};
+use serde::{
+	Deserialize, Deserializer, Serialize,
+	de::{self, SeqAccess, Visitor},
+};
+use sha2::{Digest, Sha256};
 use sqlx::prelude::FromRow;
-use time::{Date, OffsetDateTime, format_description::well_known::Iso8601};
+use time::{OffsetDateTime, format_description::well_known::Iso8601};
+use tokio::sync::mpsc;
 use uuid::Uuid;
 
 use crate::{
-	db::{Database, InsertMod},
+	db::{Database, ImportTransaction, InsertMod, InsertModDependency, InsertModVersion},
 	env::Env,
 };
 
-type Mods = Vec<ModRaw>;
+mod icons;
+pub use icons::IconStore;
+
+mod progress;
+pub use progress::{ImportPhase, ImportProgress, ProgressReport};
+
+mod schedule;
+pub use schedule::{CronSchedule, ImportSchedule};
+
+mod snapshots;
+pub use snapshots::{Snapshot, parse_name as parse_snapshot_name};
 
-const CACHE_FILE: &str = "data/mods_cache.json";
-const THUNDERSTORE_API_URL: &str = "https://thunderstore.io/c/lethal-company/api/v1/package/";
+mod source;
+pub use source::{ModSource, ThunderstoreSource, UploadSource};
+
+/// community of the mods imported before multiple communities were supported
+pub const DEFAULT_COMMUNITY: &str = "lethal-company";
+
+/// how long to wait before trying again after a failed import
+const FAILED_IMPORT_RETRY_DELAY: time::Duration = time::Duration::HOUR;
 
 #[allow(dead_code)]
 #[derive(Debug, PartialEq, Serialize, Eq, FromRow)]
@@ -33,7 +50,189 @@ pub struct Mod {
 	pub icon_url: String,
 	pub package_url: String,
 	pub id: Uuid,
+	pub delisted: bool,
 	pub categories: Vec<String>,
+	#[serde(with = "time::serde::iso8601")]
+	pub updated_date: OffsetDateTime,
+	#[serde(with = "time::serde::iso8601")]
+	pub date_created: OffsetDateTime,
+	pub donation_link: Option<String>,
+	pub pinned: bool,
+	/// homepage given in the most recent version
+	pub website_url: Option<String>,
+	/// size of the most recent version in bytes
+	pub file_size: i64,
+	/// downloads of all versions combined
+	pub downloads: i64,
+	/// file of the icon in the icon cache
+	pub icon_file: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct Version {
+	pub version_number: String,
+	pub description: String,
+	pub downloads: i64,
+	pub file_size: i64,
+	pub download_url: String,
+	#[serde(with = "time::serde::iso8601")]
+	pub date_created: OffsetDateTime,
+	pub is_active: bool,
+}
+
+/// Most recent version of a mod
+#[derive(Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct PackageVersion {
+	pub full_name: String,
+	pub version_number: String,
+}
+
+/// A mod found by its full name, along with the user's existing rating of it
+#[derive(Debug, PartialEq, Eq, FromRow)]
+pub struct FullNameMatch {
+	pub full_name: String,
+	pub id: Uuid,
+	pub rating: Option<Rating>,
+}
+
+/// Ratings of a mod by all users
+#[derive(Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct RatingCounts {
+	pub likes: i64,
+	pub dislikes: i64,
+}
+
+/// A mod found by searching, along with the user's rating of it
+#[derive(Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct SearchResult {
+	#[sqlx(flatten)]
+	#[serde(flatten)]
+	pub modd: Mod,
+	pub rating: Option<Rating>,
+}
+
+/// A mod the user has rated, along with the rating
+#[derive(Debug, PartialEq, Eq, FromRow)]
+pub struct RatedMod {
+	#[sqlx(flatten)]
+	pub modd: Mod,
+	pub rating: Rating,
+	/// None for ratings made before rating times were stored
+	pub rated_at: Option<OffsetDateTime>,
+}
+
+/// One page of a list split into pages
+#[derive(Debug)]
+pub struct Page<T> {
+	pub items: Vec<T>,
+	/// number of items on all pages
+	pub total: i64,
+}
+
+/// A mod required by another mod. `mod_id` is None if the dependency can't be
+/// found among the imported mods.
+#[derive(Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct Dependency {
+	pub full_name: String,
+	pub mod_id: Option<Uuid>,
+	pub package_url: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, FromRow, Serialize)]
+pub struct UnresolvedDependency {
+	pub mod_full_name: String,
+	pub dependency_full_name: String,
+	pub dependency_version: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, sqlx::Type)]
+#[sqlx(type_name = "import_trigger")]
+pub enum ImportTrigger {
+	Startup,
+	Expiration,
+	Manual,
+	/// an admin rolled the mods back to a snapshot
+	Rollback,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, sqlx::Type)]
+#[sqlx(type_name = "import_source")]
+pub enum ImportSource {
+	Download,
+	Cache,
+	Snapshot,
+	/// a package list uploaded by an admin
+	Upload,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, sqlx::Type)]
+#[sqlx(type_name = "import_outcome")]
+pub enum ImportOutcome {
+	Running,
+	Success,
+	Failure,
+}
+
+/// One import attempt, along with its summary if the import got that far
+#[derive(Debug, PartialEq, Eq, FromRow)]
+pub struct ImportRun {
+	pub id: i32,
+	pub started_at: OffsetDateTime,
+	pub finished_at: Option<OffsetDateTime>,
+	pub trigger: ImportTrigger,
+	pub source: ImportSource,
+	pub outcome: ImportOutcome,
+	pub error: Option<String>,
+	pub added: Option<i32>,
+	pub updated: Option<i32>,
+	pub unchanged: Option<i32>,
+	pub failed: Option<i32>,
+	pub delisted: Option<i32>,
+	/// how many faulty entries were found in the package lists
+	pub problems: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, sqlx::Type)]
+#[sqlx(type_name = "import_problem_kind")]
+pub enum ImportProblemKind {
+	NoVersions,
+	UnknownCategory,
+	InvalidVersion,
+	InvalidDependency,
+	/// the mod can't be imported without a valid id
+	InvalidId,
+	/// the mod can't be imported without a valid update date
+	InvalidDate,
+}
+
+/// A faulty entry in a package list. `mod_id` is kept as text, as it might not be a valid uuid.
+#[derive(Debug, Clone, PartialEq, Eq, FromRow, Serialize)]
+pub struct ImportProblem {
+	pub community: String,
+	pub mod_name: String,
+	pub mod_id: String,
+	pub kind: ImportProblemKind,
+	pub reason: String,
+}
+
+/// How many mods were written to db during an import
+#[derive(Debug, Default, PartialEq, Eq, Clone, FromRow, Serialize)]
+pub struct ImportSummary {
+	pub added: i32,
+	pub updated: i32,
+	pub unchanged: i32,
+	pub failed: i32,
+	pub delisted: i32,
+}
+
+impl ImportSummary {
+	fn add(&mut self, other: &ImportSummary) {
+		self.added += other.added;
+		self.updated += other.updated;
+		self.unchanged += other.unchanged;
+		self.failed += other.failed;
+		self.delisted += other.delisted;
+	}
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, FromRow, Serialize)]
@@ -42,7 +241,7 @@ pub struct Category {
 	pub id: i32,
 }
 
-#[derive(Debug, Deserialize, Clone, Copy, sqlx::Type)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, sqlx::Type)]
 #[sqlx(type_name = "rating_type")]
 pub enum Rating {
 	Like,
@@ -57,7 +256,7 @@ impl Display for Rating {
 
 #[allow(dead_code)]
 #[derive(Debug, Deserialize)]
-struct ModRaw {
+pub struct ModRaw {
 	name: String,
 	full_name: String,
 	owner: String,
@@ -93,21 +292,66 @@ struct ModVersion {
 }
 
 impl ModRaw {
+	fn problem(&self, community: &str, kind: ImportProblemKind, reason: String) -> ImportProblem {
+		log::warn!(
+			"Faulty entry for mod '{}' (id='{}'): {}",
+			self.name,
+			self.uuid4,
+			reason
+		);
+
+		ImportProblem {
+			community: community.to_string(),
+			mod_name: self.name.clone(),
+			mod_id: self.uuid4.clone(),
+			kind,
+			reason,
+		}
+	}
+
+	/// Faulty parts of the entry are left out and added to `problems`. Returns the problem
+	/// instead if the whole mod can't be imported.
 	fn to_insertable<'a>(
 		&'a self,
+		community: &'a str,
 		categories: &'a HashMap<String, Category>,
-	) -> Result<InsertMod<'a>, Box<dyn Error>> {
+		problems: &mut Vec<ImportProblem>,
+	) -> Result<InsertMod<'a>, ImportProblem> {
+		let uuid = Uuid::try_parse(&self.uuid4).map_err(|err| {
+			self.problem(
+				community,
+				ImportProblemKind::InvalidId,
+				format!("invalid id: {err}"),
+			)
+		})?;
+		let updated_date =
+			OffsetDateTime::parse(&self.date_updated, &Iso8601::DEFAULT).map_err(|err| {
+				self.problem(
+					community,
+					ImportProblemKind::InvalidDate,
+					format!("can't parse update date '{}': {err}", self.date_updated),
+				)
+			})?;
+		let date_created =
+			OffsetDateTime::parse(&self.date_created, &Iso8601::DEFAULT).map_err(|err| {
+				self.problem(
+					community,
+					ImportProblemKind::InvalidDate,
+					format!("can't parse creation date '{}': {err}", self.date_created),
+				)
+			})?;
+
 		// assume that the first version in list in the most recent
 		let most_recent = self.versions.first();
 
 		let (description, icon_url) = if let Some(most_recent) = most_recent {
 			(most_recent.description.as_str(), most_recent.icon.as_str())
 		} else {
-			log::error!(
-				"Faulty entry for mod '{}' (id='{}'): mod info found, but no versions of the mod found.",
-				self.name,
-				self.uuid4
-			);
+			problems.push(self.problem(
+				community,
+				ImportProblemKind::NoVersions,
+				"mod info found, but no versions of the mod found".to_string(),
+			));
 
 			("<No description available>", "")
 		};
@@ -115,210 +359,1786 @@ impl ModRaw {
 		let category_ids = self
 			.categories
 			.iter()
-			.map(|ct_name| {
+			.filter_map(|ct_name| {
 				let category = categories.get(ct_name);
 				if category.is_none() {
-					log::error!(
-						"Faulty entry for mod '{}' (id='{}'): can't find category id of '{}'",
-						self.name,
-						self.uuid4,
-						ct_name
-					);
+					problems.push(self.problem(
+						community,
+						ImportProblemKind::UnknownCategory,
+						format!("can't find category id of '{ct_name}'"),
+					));
 				}
-				return category;
+				category
 			})
-			.filter_map(|option| option)
 			.map(|ct| &ct.id)
 			.collect::<HashSet<_>>();
 
-		let uuid = Uuid::try_parse(&self.uuid4)?;
-		let date = Date::parse(&self.date_updated, &Iso8601::DEFAULT)?;
+		let versions = self
+			.versions
+			.iter()
+			.filter_map(|version| {
+				version
+					.to_insertable(uuid)
+					.inspect_err(|err| {
+						problems.push(self.problem(
+							community,
+							ImportProblemKind::InvalidVersion,
+							format!("can't convert version '{}': {err}", version.version_number),
+						))
+					})
+					.ok()
+			})
+			.collect();
+
+		let dependencies = most_recent
+			.into_iter()
+			.flat_map(|version| version.dependencies.iter())
+			.filter_map(|dependency| {
+				let parsed = parse_dependency(dependency);
+				if parsed.is_none() {
+					problems.push(self.problem(
+						community,
+						ImportProblemKind::InvalidDependency,
+						format!("can't parse dependency '{dependency}'"),
+					));
+				}
+				parsed
+			})
+			.map(|(full_name, version)| InsertModDependency {
+				mod_id: uuid,
+				full_name,
+				version,
+			})
+			.collect();
 
 		Ok(InsertMod {
 			uuid4: uuid,
+			fingerprint: self.fingerprint(community),
+			community,
 			name: &self.name,
 			description,
 			icon_url,
 			full_name: &self.full_name,
 			owner: &self.owner,
 			package_url: &self.package_url,
-			updated_date: date,
+			updated_date,
+			date_created,
+			donation_link: self
+				.donation_link
+				.as_deref()
+				.filter(|link| !link.is_empty()),
+			is_pinned: self.is_pinned,
+			website_url: most_recent
+				.map(|version| version.website_url.as_str())
+				.filter(|url| !url.is_empty()),
+			file_size: most_recent.map_or(0, |version| version.file_size),
+			downloads: self.versions.iter().map(|version| version.downloads).sum(),
 			rating: self.rating_score,
 			is_deprecated: self.is_deprecated,
 			has_nsfw_content: self.has_nsfw_content,
 			category_ids,
+			versions,
+			dependencies,
+		})
+	}
+}
+
+impl ModRaw {
+	/// Identifies the state of the listing of the mod in the community, a mod whose fingerprint
+	/// hasn't changed since the previous import is not written to db again. Download counts and
+	/// ratings change constantly so they are left out and updated separately for unchanged mods.
+	fn fingerprint(&self, community: &str) -> String {
+		let mut categories = self.categories.iter().collect::<Vec<_>>();
+		categories.sort();
+
+		let mut hasher = Sha256::new();
+		hasher.update(community);
+		hasher.update([0]);
+		hasher.update(&self.date_updated);
+		hasher.update([
+			self.is_deprecated as u8,
+			self.has_nsfw_content as u8,
+			self.is_pinned as u8,
+		]);
+		hasher.update(self.donation_link.as_deref().unwrap_or_default());
+		hasher.update([0]);
+		for category in categories {
+			hasher.update(category);
+			hasher.update([0]);
+		}
+
+		format!("{:x}", hasher.finalize())
+	}
+}
+
+/// Split a dependency string such as `BepInEx-BepInExPack-5.4.2100` into the
+/// full name of the mod (`BepInEx-BepInExPack`) and the required version (`5.4.2100`)
+fn parse_dependency(dependency: &str) -> Option<(&str, &str)> {
+	let (full_name, version) = dependency.rsplit_once('-')?;
+	if full_name.is_empty() || version.is_empty() || !full_name.contains('-') {
+		return None;
+	}
+
+	Some((full_name, version))
+}
+
+impl ModVersion {
+	fn to_insertable(&self, mod_id: Uuid) -> Result<InsertModVersion<'_>, Box<dyn Error>> {
+		let uuid = Uuid::try_parse(&self.uuid4)?;
+		let date_created = OffsetDateTime::parse(&self.date_created, &Iso8601::DEFAULT)?;
+
+		Ok(InsertModVersion {
+			uuid4: uuid,
+			mod_id,
+			version_number: &self.version_number,
+			description: &self.description,
+			downloads: self.downloads,
+			file_size: self.file_size,
+			download_url: &self.download_url,
+			date_created,
+			is_active: self.is_active,
 		})
 	}
 }
 
 pub async fn import_mods_if_expired(db: &Database, env: &Env) -> Result<(), Box<dyn Error>> {
 	if are_mods_expired(db, env).await? {
-		do_import_mods(db, env).await?;
+		do_import_mods(db, env, ImportTrigger::Startup, &ImportProgress::default()).await?;
 	}
 
 	Ok(())
 }
 
-pub async fn are_mods_expired(db: &Database, env: &Env) -> Result<bool, Box<dyn Error>> {
-	let options = env.mod_refresh_options.clone();
+/// Before multiple communities were supported, the package list of the default community was
+/// cached directly in the cache directory. Move it to the directory of the community so it's
+/// still used.
+pub fn move_legacy_cache(env: &Env) -> Result<(), std::io::Error> {
+	ThunderstoreSource::new(env, DEFAULT_COMMUNITY)
+		.adopt_cache(&env.cache_dir.join("mods_cache.json"))
+}
 
-	let duration = match options {
-		ModRefreshOptions::NoRefresh => return Ok(false),
-		ModRefreshOptions::CacheOnly(duration) => duration,
-		ModRefreshOptions::DownloadIfExpired(duration) => duration,
+pub async fn are_mods_expired(db: &Database, env: &Env) -> Result<bool, Box<dyn Error>> {
+	let Some(schedule) = env.mod_refresh_options.schedule() else {
+		return Ok(false);
 	};
 
 	let last_import = db.latest_mod_import_date().await?;
+	let last_failure = db.latest_failed_import_date().await?;
 	let now = OffsetDateTime::now_utc();
-	let result = is_expired(last_import, now, duration);
+	let result = is_expired(last_import, last_failure, now, schedule);
 
-	return Ok(result);
+	Ok(result)
 }
 
-pub async fn do_import_mods(db: &Database, env: &Env) -> Result<(), Box<dyn Error>> {
-	let options = env.mod_refresh_options.clone();
+/// When the next import is due according to the schedule. None if mods aren't refreshed or
+/// the schedule never runs again.
+pub async fn next_import_time(
+	db: &Database,
+	env: &Env,
+) -> Result<Option<OffsetDateTime>, Box<dyn Error>> {
+	let Some(schedule) = env.mod_refresh_options.schedule() else {
+		return Ok(None);
+	};
 
-	if options == ModRefreshOptions::NoRefresh {
-		return Ok(());
-	}
+	let last_import = db.latest_mod_import_date().await?;
+	let last_failure = db.latest_failed_import_date().await?;
+	let next = next_import(
+		last_import,
+		last_failure,
+		OffsetDateTime::now_utc(),
+		schedule,
+	);
+
+	Ok(next)
+}
 
-	let should_download_mods = match options {
-		ModRefreshOptions::DownloadIfExpired(_) => true,
-		_ => false,
+/// Import mods and record the attempt in the import history
+pub async fn do_import_mods(
+	db: &Database,
+	env: &Env,
+	trigger: ImportTrigger,
+	progress: &ImportProgress,
+) -> Result<(), Box<dyn Error>> {
+	let source = match (&env.mod_refresh_options, &env.cache_snapshot) {
+		(ModRefreshOptions::NoRefresh, _) => return Ok(()),
+		(ModRefreshOptions::CacheOnly(_), Some(_)) => ImportSource::Snapshot,
+		(ModRefreshOptions::CacheOnly(_), None) => ImportSource::Cache,
+		(ModRefreshOptions::DownloadIfExpired(_), _) => ImportSource::Download,
 	};
 
-	if should_download_mods {
-		let mods_json = download_mods_json().await?;
-		save_mods_to_cache(&mods_json)?;
+	let mod_sources = env
+		.communities
+		.iter()
+		.map(|community| {
+			let mod_source = ThunderstoreSource::new(env, community);
+			match (source, &env.cache_snapshot) {
+				(ImportSource::Snapshot, Some(snapshot)) => mod_source.at_snapshot(snapshot),
+				_ => mod_source,
+			}
+		})
+		.collect::<Vec<_>>();
+
+	import_and_record_run(db, env, trigger, source, &mod_sources, progress).await
+}
+
+/// Import a snapshot of the package list of a community, rolling the mods of the community
+/// back to the state they were in when the snapshot was taken
+pub async fn rollback_to_snapshot(
+	db: &Database,
+	env: &Env,
+	community: &str,
+	snapshot: &str,
+	progress: &ImportProgress,
+) -> Result<(), Box<dyn Error>> {
+	log::info!("Rolling mods of '{community}' back to snapshot '{snapshot}'");
+	let mod_source = ThunderstoreSource::new(env, community).at_snapshot(snapshot);
+
+	import_and_record_run(
+		db,
+		env,
+		ImportTrigger::Rollback,
+		ImportSource::Snapshot,
+		&[mod_source],
+		progress,
+	)
+	.await
+}
+
+/// Import the package list of a community uploaded by an admin. The upload is removed
+/// afterwards, whether the import succeeded or not.
+pub async fn import_upload(
+	db: &Database,
+	env: &Env,
+	community: &str,
+	progress: &ImportProgress,
+) -> Result<(), Box<dyn Error>> {
+	log::info!("Importing uploaded package list of '{community}'");
+	let mod_source = UploadSource::new(env, community);
+
+	let result = import_and_record_run(
+		db,
+		env,
+		ImportTrigger::Manual,
+		ImportSource::Upload,
+		std::slice::from_ref(&mod_source),
+		progress,
+	)
+	.await;
+
+	mod_source.remove();
+	result
+}
+
+/// Wraps the import in an import run, so that failed imports show up in the history too
+async fn import_and_record_run<S>(
+	db: &Database,
+	env: &Env,
+	trigger: ImportTrigger,
+	source: ImportSource,
+	mod_sources: &[S],
+	progress: &ImportProgress,
+) -> Result<(), Box<dyn Error>>
+where
+	S: ModSource + Clone + Send + 'static,
+{
+	let run_id = db.start_import_run(trigger, source).await?;
+	let result = run_import(db, env, source, mod_sources, run_id, progress).await;
+
+	let error = result.as_ref().err().map(|err| err.to_string());
+	db.finish_import_run(run_id, error.as_deref()).await?;
+
+	result
+}
+
+async fn run_import<S>(
+	db: &Database,
+	env: &Env,
+	source: ImportSource,
+	mod_sources: &[S],
+	run_id: i32,
+	progress: &ImportProgress,
+) -> Result<(), Box<dyn Error>>
+where
+	S: ModSource + Clone + Send + 'static,
+{
+	let failed = |mod_source: &S, err: Box<dyn Error>| {
+		format!(
+			"Importing mods of '{}' failed: {err}",
+			mod_source.community()
+		)
+	};
+
+	// download everything first, so the transaction isn't kept open while waiting for downloads
+	if source == ImportSource::Download {
+		for mod_source in mod_sources {
+			progress.importing(mod_source.community());
+			progress.phase(ImportPhase::Downloading);
+			mod_source
+				.download(progress)
+				.await
+				.map_err(|err| failed(mod_source, err))?;
+		}
+	}
+
+	// dropping the transaction on error rolls back the mods saved so far
+	let mut tx = db.begin_import(progress.clone()).await?;
+	let mut summary = ImportSummary::default();
+
+	for mod_source in mod_sources {
+		progress.importing(mod_source.community());
+		let community_summary =
+			save_cached_mods_to_db(db, &mut tx, env, mod_source, run_id, progress)
+				.await
+				.map_err(|err| failed(mod_source, err))?;
+
+		summary.add(&community_summary);
+	}
+
+	// last chance to cancel, the import can't be stopped once it's committed
+	progress.check_cancelled()?;
+	tx.commit().await?;
+
+	db.insert_import_summary(&summary, run_id, OffsetDateTime::now_utc())
+		.await?;
+
+	let unresolved = db.get_unresolved_dependencies().await?;
+	if !unresolved.is_empty() {
+		log::warn!(
+			"{} mod dependencies can't be resolved against the imported mods",
+			unresolved.len()
+		);
 	}
 
-	let mods = load_mods_from_cache()?;
-	save_mods_to_db(db, &mods, env).await?;
 	db.set_mods_imported_date(OffsetDateTime::now_utc()).await?;
 
+	// the icon urls are the same whatever the package list came from, so icons are fetched
+	// after every import. Until an icon is fetched the placeholder is shown in its place.
+	icons::fetch_icons(db, env, progress)
+		.await
+		.inspect_err(|err| log::error!("Failed to fetch mod icons: {err}"))
+		.ok();
+
 	Ok(())
 }
 
-#[derive(Debug, Clone, Default)]
-pub struct ResponseHandler {
-	data: Vec<u8>,
+/// Visits the top level array of the package list, handing out mods in chunks
+/// instead of collecting all of them in memory
+struct ChunkVisitor<F> {
+	chunk_size: usize,
+	on_chunk: F,
 }
 
-impl Handler for ResponseHandler {
-	fn write(&mut self, data: &[u8]) -> Result<usize, WriteError> {
-		self.data.extend_from_slice(data);
-		Ok(data.len())
-	}
-}
+impl<'de, F> Visitor<'de> for ChunkVisitor<F>
+where
+	F: FnMut(Vec<ModRaw>) -> Result<(), String>,
+{
+	type Value = usize;
 
-impl ResponseHandler {
-	fn new() -> Self {
-		Self::default()
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("a list of mods")
 	}
 
-	fn to_string(self) -> Result<String, FromUtf8Error> {
-		String::from_utf8(self.data)
+	fn visit_seq<A>(mut self, mut seq: A) -> Result<Self::Value, A::Error>
+	where
+		A: SeqAccess<'de>,
+	{
+		let mut count = 0;
+		let mut chunk = Vec::with_capacity(self.chunk_size);
+
+		while let Some(modd) = seq.next_element::<ModRaw>()? {
+			chunk.push(modd);
+			count += 1;
+
+			if chunk.len() >= self.chunk_size {
+				(self.on_chunk)(std::mem::take(&mut chunk)).map_err(de::Error::custom)?;
+			}
+		}
+
+		if !chunk.is_empty() {
+			(self.on_chunk)(chunk).map_err(de::Error::custom)?;
+		}
+
+		Ok(count)
 	}
 }
 
-async fn download_mods_json() -> Result<String, Box<dyn Error>> {
-	assert!(!cfg!(test), "Trying to load mod cache in tests");
+/// Parse a package list json, calling `on_chunk` with at most `chunk_size` mods at a time.
+/// Returns the total number of mods parsed.
+fn parse_mods_in_chunks(
+	reader: impl Read,
+	chunk_size: usize,
+	on_chunk: impl FnMut(Vec<ModRaw>) -> Result<(), String>,
+) -> Result<usize, serde_json::Error> {
+	let mut deserializer = serde_json::Deserializer::from_reader(reader);
+	let count = deserializer.deserialize_seq(ChunkVisitor {
+		chunk_size,
+		on_chunk,
+	})?;
+	deserializer.end()?;
 
-	let mut easy = Easy2::new(ResponseHandler::new());
-	easy.url(THUNDERSTORE_API_URL)?;
-	easy.get(true)?;
+	Ok(count)
+}
 
-	log::info!("Starting mods json download");
-	let actor = CurlActor::new();
-	let result = actor
-		.send_request(easy)
-		.await?
-		.get_ref()
-		.to_owned()
-		.to_string()?;
+/// Save the cached package list of the source in `tx`. Problems are saved outside of the
+/// transaction, so that they're kept even if the import fails.
+async fn save_cached_mods_to_db<S>(
+	db: &Database,
+	tx: &mut ImportTransaction,
+	env: &Env,
+	mod_source: &S,
+	run_id: i32,
+	progress: &ImportProgress,
+) -> Result<ImportSummary, Box<dyn Error>>
+where
+	S: ModSource + Clone + Send + 'static,
+{
+	let file = mod_source.open_cache()?;
+	let chunk_size = env.sql_chunk_size;
+	let community = mod_source.community();
 
-	Ok(result)
-}
+	// parsing is blocking, do it on another thread and receive the mods in chunks
+	let (sender, mut receiver) = mpsc::channel(2);
+	let parser_source = mod_source.clone();
+	let parser = actix_rt::task::spawn_blocking(move || {
+		parser_source.parse(file, chunk_size, |chunk| {
+			sender
+				.blocking_send(chunk)
+				.map_err(|_| "mod import was stopped".to_string())
+		})
+	});
 
-fn save_mods_to_cache(mods_json: &String) -> Result<(), Box<dyn Error>> {
-	assert!(!cfg!(test), "Trying to save mods to cache in tests");
+	log::info!("Savings mods of '{community}' to db");
+	let mut categories = HashMap::new();
+	let mut summary = ImportSummary::default();
+	let mut listed_mod_ids = Vec::new();
+	let mut problems = Vec::new();
+	progress.phase(ImportPhase::Parsing);
+	while let Some(mods) = receiver.recv().await {
+		progress.check_cancelled()?;
 
-	log::debug!("Saving mods json to cache");
+		let chunk_summary =
+			save_mods_to_db(tx, &mods, community, &mut categories, &mut problems, env).await?;
+		summary.add(&chunk_summary);
 
-	let path = Path::new(CACHE_FILE);
-	if let Some(parent) = path.parent() {
-		std::fs::create_dir_all(parent)?;
+		db.insert_import_problems(run_id, &problems, env.sql_chunk_size)
+			.await?;
+		problems.clear();
+
+		progress.mods_processed(mods.len());
+		progress.phase(ImportPhase::Parsing);
+
+		listed_mod_ids.extend(mods.iter().filter_map(|m| Uuid::try_parse(&m.uuid4).ok()));
 	}
 
-	std::fs::write(path, mods_json)?;
-	Ok(())
-}
+	let mods_count = parser.await?.map_err(|err| err as Box<dyn Error>)?;
+
+	// an empty package list is more likely an upstream problem than every mod getting removed
+	if mods_count > 0 {
+		let delisted = tx
+			.update_delisted_mods(community, &listed_mod_ids, OffsetDateTime::now_utc())
+			.await?;
+		summary.delisted = delisted as i32;
+	} else {
+		log::warn!("Package list of '{community}' is empty, not marking any mods as delisted");
+	}
+
+	log::info!(
+		"Processed {mods_count} mods of '{community}': {} added, {} updated, {} unchanged, {} failed, {} delisted",
+		summary.added,
+		summary.updated,
+		summary.unchanged,
+		summary.failed,
+		summary.delisted
+	);
 
-fn load_mods_from_cache() -> Result<Mods, Box<dyn Error>> {
-	let str = std::fs::read_to_string(CACHE_FILE)?;
-	let mods = serde_json::from_str(&str)?;
-	Ok(mods)
+	Ok(summary)
 }
 
 fn is_expired(
 	last_import: Option<OffsetDateTime>,
+	last_failure: Option<OffsetDateTime>,
 	now: OffsetDateTime,
-	expiration_duration: Duration,
+	schedule: &ImportSchedule,
 ) -> bool {
-	if let Some(last_import) = last_import {
-		let time_passed = now - last_import;
-		time_passed > expiration_duration
-	} else {
-		// no previous value present -> this is first time
-		true
-	}
+	next_import(last_import, last_failure, now, schedule).is_some_and(|next| next <= now)
+}
+
+/// When the next import is due after the last successful import. If an import has failed since
+/// then, it's retried only after `FAILED_IMPORT_RETRY_DELAY` has passed since the failed attempt.
+fn next_import(
+	last_import: Option<OffsetDateTime>,
+	last_failure: Option<OffsetDateTime>,
+	now: OffsetDateTime,
+	schedule: &ImportSchedule,
+) -> Option<OffsetDateTime> {
+	let scheduled = match last_import {
+		Some(last_import) => schedule.next_import(last_import)?,
+		// no previous value present -> this is first time, so due right away
+		None => now,
+	};
+
+	let retry = last_failure
+		.filter(|failure| last_import.is_none_or(|last_import| *failure > last_import))
+		.map(|failure| failure + FAILED_IMPORT_RETRY_DELAY);
+
+	Some(retry.map_or(scheduled, |retry| retry.max(scheduled)))
 }
 
 #[allow(dead_code)]
 #[derive(PartialEq, Eq, Clone)]
 pub enum ModRefreshOptions {
 	NoRefresh,
-	CacheOnly(Duration),
-	DownloadIfExpired(Duration),
+	CacheOnly(ImportSchedule),
+	DownloadIfExpired(ImportSchedule),
+}
+
+impl ModRefreshOptions {
+	/// when mods are imported, None if they aren't refreshed
+	pub fn schedule(&self) -> Option<&ImportSchedule> {
+		match self {
+			ModRefreshOptions::NoRefresh => None,
+			ModRefreshOptions::CacheOnly(schedule) => Some(schedule),
+			ModRefreshOptions::DownloadIfExpired(schedule) => Some(schedule),
+		}
+	}
 }
 
+/// Save one chunk of mods of a community, skipping mods that haven't changed since they were
+/// last saved. `categories` caches the categories of the community already in the db, it's
+/// refreshed whenever the chunk contains new categories. Faulty entries are added to `problems`.
 async fn save_mods_to_db(
-	db: &Database,
-	mods: &Vec<ModRaw>,
+	tx: &mut ImportTransaction,
+	mods: &[ModRaw],
+	community: &str,
+	categories: &mut HashMap<String, Category>,
+	problems: &mut Vec<ImportProblem>,
 	env: &Env,
-) -> Result<(), Box<dyn Error>> {
-	let category_names = mods
+) -> Result<ImportSummary, Box<dyn Error>> {
+	let new_categories = mods
 		.iter()
-		.map(|modd| modd.categories.iter())
-		.flatten()
+		.flat_map(|modd| modd.categories.iter())
+		.filter(|name| !categories.contains_key(*name))
 		.collect::<HashSet<_>>();
 
-	log::info!("Saving mod categories to db");
-	db.insert_categories(&category_names).await?;
+	if !new_categories.is_empty() {
+		log::debug!("Saving mod categories to db");
+		tx.insert_categories(community, &new_categories).await?;
 
-	let categories = db
-		.get_categories()
-		.await?
-		.into_iter()
-		.map(|ct| (ct.name.clone(), ct))
-		.collect::<HashMap<String, Category>>();
+		*categories = tx
+			.get_categories(community)
+			.await?
+			.into_iter()
+			.map(|ct| (ct.name.clone(), ct))
+			.collect();
+	}
 
-	let mods = mods
+	let insertable = mods
 		.iter()
 		.filter_map(|m| {
-			m.to_insertable(&categories)
-				.inspect_err(|err| {
-					log::warn!(
-						"Failed to convert mod '{}' (id={}) to SQL-insertable: {}",
-						m.name,
-						m.uuid4,
-						err
-					)
-				})
+			m.to_insertable(community, categories, problems)
+				.map_err(|problem| problems.push(problem))
 				.ok()
 		})
-		.collect();
-	log::info!("Savings mods to db");
-	db.insert_mods(&mods, env.sql_chunk_size).await?;
-	Ok(())
+		.collect::<Vec<_>>();
+
+	let mut summary = ImportSummary {
+		failed: (mods.len() - insertable.len()) as i32,
+		..Default::default()
+	};
+
+	let mod_ids = insertable.iter().map(|m| m.uuid4).collect::<Vec<_>>();
+	let stored_fingerprints = tx.get_mod_fingerprints(community, &mod_ids).await?;
+
+	let (changed, unchanged): (Vec<_>, Vec<_>) =
+		insertable
+			.into_iter()
+			.partition(|m| match stored_fingerprints.get(&m.uuid4) {
+				None => {
+					summary.added += 1;
+					true
+				}
+				Some(fingerprint) if *fingerprint != m.fingerprint => {
+					summary.updated += 1;
+					true
+				}
+				Some(_) => {
+					summary.unchanged += 1;
+					false
+				}
+			});
+
+	tx.insert_mods(community, &changed, env.sql_chunk_size)
+		.await?;
+	tx.update_mod_counters(community, &unchanged).await?;
+
+	Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		io::{BufRead, Write},
+		net::TcpListener,
+		path::PathBuf,
+		sync::{Arc, Mutex},
+		time::Duration,
+	};
+
+	use log::LevelFilter;
+	use serde_json::json;
+	use sqlx::{Pool, Postgres};
+	use tempfile::TempDir;
+	use time::macros::datetime;
+
+	use super::*;
+
+	fn mod_value(name: &str, id: usize, date_updated: &str) -> serde_json::Value {
+		json!({
+			"name": name,
+			"full_name": format!("Owner-{name}"),
+			"owner": "Owner",
+			"package_url": "",
+			"donation_link": null,
+			"date_created": "2025-01-01T10:00:00.000000+00:00",
+			"date_updated": date_updated,
+			"uuid4": format!("00000000-0000-0000-0000-{id:012}"),
+			"rating_score": 0,
+			"is_pinned": false,
+			"is_deprecated": false,
+			"has_nsfw_content": false,
+			"categories": ["Misc"],
+			"versions": []
+		})
+	}
+
+	fn package_list(names: &[&str]) -> String {
+		let mods = names
+			.iter()
+			.enumerate()
+			.map(|(id, name)| mod_value(name, id, "2025-01-01T10:00:00.000000+00:00"))
+			.collect::<Vec<_>>();
+		serde_json::to_string(&mods).unwrap()
+	}
+
+	/// version whose id is derived from `downloads`
+	fn version_value(number: &str, downloads: i64) -> serde_json::Value {
+		json!({
+			"name": "a",
+			"full_name": format!("Owner-a-{number}"),
+			"description": "",
+			"icon": "",
+			"version_number": number,
+			"dependencies": [],
+			"download_url": "",
+			"downloads": downloads,
+			"date_created": "2025-01-01T10:00:00.000000+00:00",
+			"website_url": "",
+			"is_active": true,
+			"uuid4": format!("00000000-0000-0000-0001-{downloads:012}"),
+			"file_size": 1000,
+		})
+	}
+
+	fn raw_mod(name: &str, id: usize, date_updated: &str) -> ModRaw {
+		serde_json::from_value(mod_value(name, id, date_updated)).unwrap()
+	}
+
+	fn test_env() -> Env {
+		Env {
+			port: 0,
+			log_level: LevelFilter::Off,
+			sql_chunk_size: 2,
+			mod_refresh_options: ModRefreshOptions::NoRefresh,
+			db_url: String::new(),
+			communities: vec![DEFAULT_COMMUNITY.to_string()],
+			thunderstore_url: String::new(),
+			cache_dir: PathBuf::new(),
+			download_retries: 0,
+			download_retry_delay: Duration::ZERO,
+			cache_snapshots: 0,
+			cache_snapshot: None,
+			icon_max_size: 0,
+		}
+	}
+
+	/// env which downloads the package list from `thunderstore_url` into an empty cache directory,
+	/// the directory is removed when the returned `TempDir` is dropped
+	fn download_env(thunderstore_url: String) -> (Env, TempDir) {
+		let cache_dir = tempfile::tempdir().unwrap();
+
+		let env = Env {
+			mod_refresh_options: ModRefreshOptions::DownloadIfExpired(ImportSchedule::Interval(
+				Duration::from_secs(3600),
+			)),
+			thunderstore_url,
+			cache_dir: cache_dir.path().to_path_buf(),
+			download_retries: 2,
+			cache_snapshots: 5,
+			..test_env()
+		};
+
+		(env, cache_dir)
+	}
+
+	fn cache_file(env: &Env) -> PathBuf {
+		env.cache_dir
+			.join(DEFAULT_COMMUNITY)
+			.join("mods_cache.json")
+	}
+
+	fn snapshot_dir(env: &Env) -> PathBuf {
+		env.cache_dir.join(DEFAULT_COMMUNITY).join("snapshots")
+	}
+
+	fn write_cache(env: &Env, content: &str) {
+		let path = cache_file(env);
+		std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+		std::fs::write(path, content).unwrap();
+	}
+
+	fn http_response(status: &str, headers: &[&str], body: &str) -> Vec<u8> {
+		let mut response = format!("HTTP/1.1 {status}\r\n");
+		for header in headers {
+			response.push_str(&format!("{header}\r\n"));
+		}
+		response.push_str(&format!(
+			"Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+			body.len()
+		));
+		response.into_bytes()
+	}
+
+	fn json_response(body: &str) -> Vec<u8> {
+		http_response("200 OK", &["Content-Type: application/json"], body)
+	}
+
+	/// Local stand-in for Thunderstore
+	struct StandIn {
+		url: String,
+		/// headers of the requests received so far
+		requests: Arc<Mutex<Vec<String>>>,
+	}
+
+	impl StandIn {
+		fn requests(&self) -> Vec<String> {
+			self.requests.lock().unwrap().clone()
+		}
+	}
+
+	/// Start a stand-in server. `respond` gets the headers of a request and the number of
+	/// requests received before it, and returns the raw HTTP response.
+	fn serve(respond: impl Fn(&str, usize) -> Vec<u8> + Send + 'static) -> StandIn {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let url = format!("http://{}", listener.local_addr().unwrap());
+		let requests = Arc::new(Mutex::new(Vec::new()));
+
+		let received = requests.clone();
+		std::thread::spawn(move || {
+			for stream in listener.incoming() {
+				let Ok(mut stream) = stream else {
+					continue;
+				};
+
+				// read the request until the blank line ending the headers
+				let mut reader = std::io::BufReader::new(&stream);
+				let mut request = String::new();
+				while reader.read_line(&mut request).is_ok_and(|read| read > 2) {}
+
+				let count = {
+					let mut received = received.lock().unwrap();
+					received.push(request.clone());
+					received.len() - 1
+				};
+
+				stream.write_all(&respond(&request, count)).ok();
+			}
+		});
+
+		StandIn { url, requests }
+	}
+
+	fn serve_always(response: Vec<u8>) -> StandIn {
+		serve(move |_, _| response.clone())
+	}
+
+	async fn latest_run(db: &Database) -> ImportRun {
+		db.get_import_runs(1).await.unwrap().remove(0)
+	}
+
+	async fn all_mods(db: &Database) -> Vec<Mod> {
+		let options = crate::db::ModQueryOptions {
+			limit: 100,
+			..Default::default()
+		};
+		db.get_mods(&options, 0).await.unwrap()
+	}
+
+	#[sqlx::test]
+	async fn saving_only_new_and_changed_mods(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let env = test_env();
+		let mut tx = db.begin_import(ImportProgress::default()).await.unwrap();
+		let mut categories = HashMap::new();
+		let mut problems = Vec::new();
+
+		let old_date = "2025-01-01T10:00:00.000000+00:00";
+		let new_date = "2025-02-02T10:00:00.000000+00:00";
+
+		let mods = vec![raw_mod("a", 1, old_date), raw_mod("b", 2, old_date)];
+		let summary = save_mods_to_db(
+			&mut tx,
+			&mods,
+			DEFAULT_COMMUNITY,
+			&mut categories,
+			&mut problems,
+			&env,
+		)
+		.await
+		.unwrap();
+		let expected = ImportSummary {
+			added: 2,
+			..Default::default()
+		};
+		assert_eq!(expected, summary);
+
+		let mut faulty = raw_mod("faulty", 4, old_date);
+		faulty.uuid4 = "not an uuid".to_string();
+
+		let mods = vec![
+			raw_mod("a", 1, old_date),
+			raw_mod("b", 2, new_date),
+			raw_mod("c", 3, old_date),
+			faulty,
+		];
+		let summary = save_mods_to_db(
+			&mut tx,
+			&mods,
+			DEFAULT_COMMUNITY,
+			&mut categories,
+			&mut problems,
+			&env,
+		)
+		.await
+		.unwrap();
+		let expected = ImportSummary {
+			added: 1,
+			updated: 1,
+			unchanged: 1,
+			failed: 1,
+			delisted: 0,
+		};
+		assert_eq!(expected, summary);
+
+		let kinds = problems
+			.iter()
+			.map(|problem| (problem.mod_name.as_str(), problem.kind))
+			.collect::<Vec<_>>();
+		assert!(kinds.contains(&("faulty", ImportProblemKind::InvalidId)));
+	}
+
+	#[sqlx::test]
+	async fn unchanged_mods_keep_their_categories(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let env = test_env();
+		let mut tx = db.begin_import(ImportProgress::default()).await.unwrap();
+		let mut categories = HashMap::new();
+		let mut problems = Vec::new();
+
+		let date = "2025-01-01T10:00:00.000000+00:00";
+		let mods = vec![raw_mod("a", 1, date), raw_mod("b", 2, date)];
+		save_mods_to_db(
+			&mut tx,
+			&mods,
+			DEFAULT_COMMUNITY,
+			&mut categories,
+			&mut problems,
+			&env,
+		)
+		.await
+		.unwrap();
+		save_mods_to_db(
+			&mut tx,
+			&mods,
+			DEFAULT_COMMUNITY,
+			&mut categories,
+			&mut problems,
+			&env,
+		)
+		.await
+		.unwrap();
+		tx.commit().await.unwrap();
+
+		let options = crate::db::ModQueryOptions {
+			community: DEFAULT_COMMUNITY.to_string(),
+			ignored_categories: HashSet::from(["Misc".to_string()]),
+			limit: 100,
+			include_deprecated: true,
+			include_nsfw: true,
+			order: crate::db::QueueOrder::Updated,
+		};
+		let result = db.get_mods(&options, 0).await.unwrap();
+		assert!(result.is_empty());
+	}
+
+	#[sqlx::test]
+	async fn mods_listed_in_several_communities(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let env = test_env();
+		let other = "content-warning";
+
+		let date = "2025-01-01T10:00:00.000000+00:00";
+		let listed = raw_mod("a", 1, date);
+		let mut other_listed = raw_mod("a", 1, date);
+		other_listed.categories = vec!["Tools".to_string()];
+
+		let mut tx = db.begin_import(ImportProgress::default()).await.unwrap();
+		let (mut categories, mut other_categories) = (HashMap::new(), HashMap::new());
+		let mut summaries = Vec::new();
+		for _ in 0..2 {
+			for (community, mods, categories) in [
+				(DEFAULT_COMMUNITY, &listed, &mut categories),
+				(other, &other_listed, &mut other_categories),
+			] {
+				let summary = save_mods_to_db(
+					&mut tx,
+					std::slice::from_ref(mods),
+					community,
+					categories,
+					&mut Vec::new(),
+					&env,
+				)
+				.await
+				.unwrap();
+				summaries.push(summary);
+			}
+		}
+		tx.commit().await.unwrap();
+
+		let added = summaries.iter().map(|s| s.added).collect::<Vec<_>>();
+		let unchanged = summaries.iter().map(|s| s.unchanged).collect::<Vec<_>>();
+		assert_eq!(vec![1, 1, 0, 0], added);
+		assert_eq!(vec![0, 0, 1, 1], unchanged);
+
+		let id = Uuid::parse_str(&listed.uuid4).unwrap();
+		let modd = db.get_mod(&id, DEFAULT_COMMUNITY).await.unwrap().unwrap();
+		assert_eq!(vec!["Misc"], modd.categories);
+		let modd = db.get_mod(&id, other).await.unwrap().unwrap();
+		assert_eq!(vec!["Tools"], modd.categories);
+
+		let options = crate::db::ModQueryOptions {
+			community: other.to_string(),
+			limit: 100,
+			..Default::default()
+		};
+		assert_eq!(1, db.get_mods(&options, 0).await.unwrap().len());
+	}
+
+	#[test]
+	fn expiration_follows_schedule() {
+		let interval = ImportSchedule::Interval(Duration::from_secs(3600));
+		let last_import = Some(datetime!(2025-01-10 04:30 UTC));
+		assert!(!is_expired(
+			last_import,
+			None,
+			datetime!(2025-01-10 05:00 UTC),
+			&interval
+		));
+		assert!(is_expired(
+			last_import,
+			None,
+			datetime!(2025-01-10 05:31 UTC),
+			&interval
+		));
+		assert!(is_expired(
+			None,
+			None,
+			datetime!(2025-01-10 05:00 UTC),
+			&interval
+		));
+
+		let daily = ImportSchedule::Cron(CronSchedule::parse("0 5 * * *", "UTC").unwrap());
+		assert!(!is_expired(
+			last_import,
+			None,
+			datetime!(2025-01-10 04:59 UTC),
+			&daily
+		));
+		assert!(is_expired(
+			last_import,
+			None,
+			datetime!(2025-01-10 05:01 UTC),
+			&daily
+		));
+	}
+
+	#[test]
+	fn failed_import_is_retried_after_delay() {
+		let daily = ImportSchedule::Cron(CronSchedule::parse("0 5 * * *", "UTC").unwrap());
+		let last_import = Some(datetime!(2025-01-09 05:00 UTC));
+		let last_failure = Some(datetime!(2025-01-10 05:00 UTC));
+
+		assert_eq!(
+			Some(datetime!(2025-01-10 06:00 UTC)),
+			next_import(
+				last_import,
+				last_failure,
+				datetime!(2025-01-10 05:01 UTC),
+				&daily
+			)
+		);
+		assert!(!is_expired(
+			last_import,
+			last_failure,
+			datetime!(2025-01-10 05:30 UTC),
+			&daily
+		));
+		assert!(is_expired(
+			last_import,
+			last_failure,
+			datetime!(2025-01-10 06:01 UTC),
+			&daily
+		));
+
+		// the first import is retried too
+		assert!(!is_expired(
+			None,
+			last_failure,
+			datetime!(2025-01-10 05:30 UTC),
+			&daily
+		));
+
+		// failures before the last successful import don't delay the next one
+		assert!(is_expired(
+			last_import,
+			Some(datetime!(2025-01-09 04:59 UTC)),
+			datetime!(2025-01-10 05:01 UTC),
+			&daily
+		));
+	}
+
+	#[test]
+	fn parsing_mods_in_chunks() {
+		let json = package_list(&["a", "b", "c", "d", "e"]);
+
+		let mut chunks = Vec::new();
+		let count = parse_mods_in_chunks(json.as_bytes(), 2, |chunk| {
+			chunks.push(chunk.into_iter().map(|m| m.name).collect::<Vec<_>>());
+			Ok(())
+		})
+		.unwrap();
+
+		assert_eq!(5, count);
+		assert_eq!(vec![vec!["a", "b"], vec!["c", "d"], vec!["e"]], chunks);
+	}
+
+	#[test]
+	fn parsing_empty_package_list() {
+		let mut chunks = 0;
+		let count = parse_mods_in_chunks("[]".as_bytes(), 2, |_| {
+			chunks += 1;
+			Ok(())
+		})
+		.unwrap();
+
+		assert_eq!(0, count);
+		assert_eq!(0, chunks);
+	}
+
+	#[test]
+	fn parsing_stops_when_chunk_is_rejected() {
+		let json = package_list(&["a", "b", "c", "d", "e"]);
+
+		let mut chunks = 0;
+		let result = parse_mods_in_chunks(json.as_bytes(), 2, |_| {
+			chunks += 1;
+			Err("stop".to_string())
+		});
+
+		assert!(result.is_err());
+		assert_eq!(1, chunks);
+	}
+
+	#[test]
+	fn parsing_malformed_package_list() {
+		let truncated = package_list(&["a", "b"]);
+		let truncated = &truncated[..truncated.len() - 10];
+
+		assert!(parse_mods_in_chunks(truncated.as_bytes(), 2, |_| Ok(())).is_err());
+		assert!(parse_mods_in_chunks("{}".as_bytes(), 2, |_| Ok(())).is_err());
+		assert!(parse_mods_in_chunks("[] []".as_bytes(), 2, |_| Ok(())).is_err());
+	}
+
+	#[sqlx::test]
+	async fn importing_downloaded_package_list(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let body = package_list(&["a", "b", "c"]);
+		let server = serve_always(json_response(&body));
+		let (env, _cache_dir) = download_env(server.url);
+
+		do_import_mods(&db, &env, ImportTrigger::Manual, &ImportProgress::default())
+			.await
+			.unwrap();
+
+		assert_eq!(3, all_mods(&db).await.len());
+		assert_eq!(body, std::fs::read_to_string(cache_file(&env)).unwrap());
+
+		let run = latest_run(&db).await;
+		assert_eq!(ImportOutcome::Success, run.outcome);
+		assert_eq!(Some(3), run.added);
+	}
+
+	#[sqlx::test]
+	async fn http_error_keeps_previous_cache(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let server = serve_always(http_response(
+			"500 Internal Server Error",
+			&["Content-Type: application/json"],
+			r#"{"detail": "Internal server error"}"#,
+		));
+		let (env, _cache_dir) = download_env(server.url.clone());
+		let previous = package_list(&["a"]);
+		write_cache(&env, &previous);
+
+		let result =
+			do_import_mods(&db, &env, ImportTrigger::Manual, &ImportProgress::default()).await;
+
+		assert!(result.is_err());
+		assert!(all_mods(&db).await.is_empty());
+		assert_eq!(previous, std::fs::read_to_string(cache_file(&env)).unwrap());
+		// the first attempt and two retries
+		assert_eq!(3, server.requests().len());
+
+		let run = latest_run(&db).await;
+		assert_eq!(ImportOutcome::Failure, run.outcome);
+		assert!(run.error.unwrap().contains("500"));
+	}
+
+	#[sqlx::test]
+	async fn partial_response_keeps_previous_cache(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let body = package_list(&["a", "b", "c"]);
+		// promise more bytes than are sent before closing the connection
+		let response = format!(
+			"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+			body.len(),
+			&body[..body.len() / 2]
+		);
+		let server = serve_always(response.into_bytes());
+		let (env, _cache_dir) = download_env(server.url);
+		let previous = package_list(&["a"]);
+		write_cache(&env, &previous);
+
+		let result =
+			do_import_mods(&db, &env, ImportTrigger::Manual, &ImportProgress::default()).await;
+
+		assert!(result.is_err());
+		assert!(all_mods(&db).await.is_empty());
+		assert_eq!(previous, std::fs::read_to_string(cache_file(&env)).unwrap());
+		assert_eq!(ImportOutcome::Failure, latest_run(&db).await.outcome);
+	}
+
+	#[sqlx::test]
+	async fn malformed_package_list_keeps_previous_cache(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let server = serve_always(json_response(r#"{"not": "a list"}"#));
+		let (env, _cache_dir) = download_env(server.url);
+		let previous = package_list(&["a"]);
+		write_cache(&env, &previous);
+
+		let result =
+			do_import_mods(&db, &env, ImportTrigger::Manual, &ImportProgress::default()).await;
+
+		assert!(result.is_err());
+		assert!(all_mods(&db).await.is_empty());
+		assert_eq!(previous, std::fs::read_to_string(cache_file(&env)).unwrap());
+		assert_eq!(ImportOutcome::Failure, latest_run(&db).await.outcome);
+	}
+
+	#[sqlx::test]
+	async fn unexpected_content_type_is_not_cached(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let server = serve_always(http_response(
+			"200 OK",
+			&["Content-Type: text/html"],
+			"<html>Service unavailable</html>",
+		));
+		let (env, _cache_dir) = download_env(server.url.clone());
+		let previous = package_list(&["a"]);
+		write_cache(&env, &previous);
+
+		let result =
+			do_import_mods(&db, &env, ImportTrigger::Manual, &ImportProgress::default()).await;
+
+		assert!(result.is_err());
+		assert_eq!(previous, std::fs::read_to_string(cache_file(&env)).unwrap());
+		// retrying won't change the content type
+		assert_eq!(1, server.requests().len());
+	}
+
+	#[sqlx::test]
+	async fn retrying_failed_download(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let body = package_list(&["a", "b"]);
+		let server = serve(move |_, count| {
+			if count == 0 {
+				http_response("503 Service Unavailable", &[], "")
+			} else {
+				json_response(&body)
+			}
+		});
+		let (env, _cache_dir) = download_env(server.url.clone());
+
+		do_import_mods(&db, &env, ImportTrigger::Manual, &ImportProgress::default())
+			.await
+			.unwrap();
+
+		assert_eq!(2, all_mods(&db).await.len());
+		assert_eq!(2, server.requests().len());
+	}
+
+	#[sqlx::test]
+	async fn fetching_icons_after_download(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let server = serve(|request, _| {
+			if request.starts_with("GET /icon.png") {
+				http_response("200 OK", &["Content-Type: image/png"], "small icon")
+			} else if request.starts_with("GET /large.png") {
+				http_response("200 OK", &["Content-Type: image/png"], &"x".repeat(2000))
+			} else if request.starts_with("GET /page.png") {
+				http_response("200 OK", &["Content-Type: text/html"], "<html></html>")
+			} else {
+				// the package list, its icons are served by this server too
+				let mods = ["icon", "large", "page"]
+					.iter()
+					.enumerate()
+					.map(|(i, name)| {
+						let mut version = version_value("1.0.0", i as i64);
+						version["icon"] = json!(format!("{{url}}/{name}.png"));
+
+						let mut value = mod_value(name, i, "2025-01-01T10:00:00.000000+00:00");
+						value["versions"] = json!([version]);
+						value
+					})
+					.collect::<Vec<_>>();
+
+				let url = request
+					.lines()
+					.find_map(|line| line.strip_prefix("Host: "))
+					.map(|host| format!("http://{}", host.trim()))
+					.unwrap_or_default();
+				json_response(&json!(mods).to_string().replace("{url}", &url))
+			}
+		});
+		let (env, _cache_dir) = download_env(server.url.clone());
+		let env = Env {
+			icon_max_size: 1000,
+			..env
+		};
+
+		do_import_mods(&db, &env, ImportTrigger::Manual, &ImportProgress::default())
+			.await
+			.unwrap();
+
+		let mods = all_mods(&db).await;
+		let icon_file = |name: &str| {
+			mods.iter()
+				.find(|m| m.name == name)
+				.and_then(|m| m.icon_file.clone())
+		};
+
+		let store = IconStore::new(&env);
+		let icon = icon_file("icon").unwrap();
+		assert!(icon.ends_with(".png"));
+		assert_eq!(
+			"small icon",
+			std::fs::read_to_string(store.path(&icon).unwrap()).unwrap()
+		);
+		assert_eq!(None, icon_file("large"));
+		assert_eq!(None, icon_file("page"));
+		assert!(db.get_mods_without_cached_icon().await.unwrap().is_empty());
+	}
+
+	/// package list of mods named `names`, whose icons are at `{icon_host}/{name}.png`
+	fn package_list_with_icons(names: &[&str], icon_host: &str) -> String {
+		let mods = names
+			.iter()
+			.enumerate()
+			.map(|(i, name)| {
+				let mut version = version_value("1.0.0", i as i64);
+				version["icon"] = json!(format!("{icon_host}/{name}.png"));
+
+				let mut value = mod_value(name, i, "2025-01-01T10:00:00.000000+00:00");
+				value["versions"] = json!([version]);
+				value
+			})
+			.collect::<Vec<_>>();
+		serde_json::to_string(&mods).unwrap()
+	}
+
+	#[sqlx::test]
+	async fn fetching_icons_after_upload(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let server = serve(|_, _| http_response("200 OK", &["Content-Type: image/png"], "icon"));
+		let (env, _cache_dir) = download_env(String::new());
+		let env = Env {
+			icon_max_size: 1000,
+			..env
+		};
+
+		let upload = UploadSource::new(&env, DEFAULT_COMMUNITY);
+		std::fs::create_dir_all(upload.file().parent().unwrap()).unwrap();
+		std::fs::write(upload.file(), package_list_with_icons(&["a"], &server.url)).unwrap();
+
+		import_upload(&db, &env, DEFAULT_COMMUNITY, &ImportProgress::default())
+			.await
+			.unwrap();
+
+		assert!(all_mods(&db).await[0].icon_file.is_some());
+		assert_eq!(1, server.requests().len());
+	}
+
+	#[sqlx::test]
+	async fn unreachable_icons_are_left_for_next_import(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let server = serve_always(http_response("503 Service Unavailable", &[], ""));
+		let (env, _cache_dir) = download_env(String::new());
+		let env = Env {
+			icon_max_size: 1000,
+			..env
+		};
+
+		let names = (0..20).map(|i| format!("mod{i}")).collect::<Vec<_>>();
+		let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+
+		let upload = UploadSource::new(&env, DEFAULT_COMMUNITY);
+		std::fs::create_dir_all(upload.file().parent().unwrap()).unwrap();
+		std::fs::write(upload.file(), package_list_with_icons(&names, &server.url)).unwrap();
+
+		import_upload(&db, &env, DEFAULT_COMMUNITY, &ImportProgress::default())
+			.await
+			.unwrap();
+
+		// fetching stops after the first icons fetched at the same time all fail
+		assert_eq!(8, server.requests().len());
+		assert_eq!(20, all_mods(&db).await.len());
+		assert_eq!(20, db.get_mods_without_cached_icon().await.unwrap().len());
+	}
+
+	#[sqlx::test]
+	async fn unchanged_package_list_is_not_downloaded_again(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let body = package_list(&["a", "b"]);
+		let server = serve(move |request, _| {
+			if request.contains("If-None-Match: \"v1\"") {
+				http_response("304 Not Modified", &["ETag: \"v1\""], "")
+			} else {
+				http_response(
+					"200 OK",
+					&["Content-Type: application/json", "ETag: \"v1\""],
+					&body,
+				)
+			}
+		});
+		let (env, _cache_dir) = download_env(server.url.clone());
+
+		do_import_mods(&db, &env, ImportTrigger::Manual, &ImportProgress::default())
+			.await
+			.unwrap();
+		do_import_mods(&db, &env, ImportTrigger::Manual, &ImportProgress::default())
+			.await
+			.unwrap();
+
+		let requests = server.requests();
+		assert_eq!(2, requests.len());
+		assert!(!requests[0].contains("If-None-Match"));
+		assert!(requests[1].contains("If-None-Match: \"v1\""));
+
+		let run = latest_run(&db).await;
+		assert_eq!(ImportOutcome::Success, run.outcome);
+		assert_eq!(Some(2), run.unchanged);
+	}
+
+	#[sqlx::test]
+	async fn resuming_interrupted_download(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let body = package_list(&["a", "b", "c"]);
+		let half = body.len() / 2;
+		let server = serve(move |_, count| {
+			if count == 0 {
+				// connection drops halfway through the list
+				format!(
+					"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nETag: \"v1\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+					body.len(),
+					&body[..half]
+				)
+				.into_bytes()
+			} else {
+				http_response(
+					"206 Partial Content",
+					&[
+						"Content-Type: application/json",
+						"ETag: \"v1\"",
+						&format!(
+							"Content-Range: bytes {half}-{}/{}",
+							body.len() - 1,
+							body.len()
+						),
+					],
+					&body[half..],
+				)
+			}
+		});
+		let (env, _cache_dir) = download_env(server.url.clone());
+
+		do_import_mods(&db, &env, ImportTrigger::Manual, &ImportProgress::default())
+			.await
+			.unwrap();
+
+		let requests = server.requests();
+		assert_eq!(2, requests.len());
+		assert!(requests[1].contains(&format!("Range: bytes={half}-")));
+		assert!(requests[1].contains("If-Range: \"v1\""));
+
+		let expected = package_list(&["a", "b", "c"]);
+		assert_eq!(expected, std::fs::read_to_string(cache_file(&env)).unwrap());
+		assert_eq!(3, all_mods(&db).await.len());
+	}
+
+	#[sqlx::test]
+	async fn rolling_back_to_snapshot(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let server = serve_always(json_response(&package_list(&["a", "b", "c"])));
+		let (env, _cache_dir) = download_env(server.url);
+
+		write_cache(&env, &package_list(&["a", "b"]));
+		snapshots::take(
+			&snapshot_dir(&env),
+			&cache_file(&env),
+			datetime!(2025-01-01 00:00 UTC),
+			5,
+		)
+		.unwrap();
+
+		do_import_mods(&db, &env, ImportTrigger::Manual, &ImportProgress::default())
+			.await
+			.unwrap();
+		assert_eq!(3, all_mods(&db).await.len());
+		assert_eq!(2, snapshots::list(&snapshot_dir(&env)).unwrap().len());
+
+		rollback_to_snapshot(
+			&db,
+			&env,
+			DEFAULT_COMMUNITY,
+			"20250101T000000Z",
+			&ImportProgress::default(),
+		)
+		.await
+		.unwrap();
+		assert_eq!(2, all_mods(&db).await.len());
+
+		let run = latest_run(&db).await;
+		assert_eq!(ImportTrigger::Rollback, run.trigger);
+		assert_eq!(ImportSource::Snapshot, run.source);
+		assert_eq!(Some(1), run.delisted);
+	}
+
+	#[sqlx::test]
+	async fn importing_uploaded_package_list(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let (env, _cache_dir) = download_env(String::new());
+		let upload = UploadSource::new(&env, DEFAULT_COMMUNITY);
+
+		// plain json
+		std::fs::create_dir_all(upload.file().parent().unwrap()).unwrap();
+		std::fs::write(upload.file(), package_list(&["a", "b"])).unwrap();
+		assert_eq!(2, upload.validate().unwrap());
+
+		import_upload(&db, &env, DEFAULT_COMMUNITY, &ImportProgress::default())
+			.await
+			.unwrap();
+		assert_eq!(2, all_mods(&db).await.len());
+		assert!(!upload.file().exists());
+
+		// gzip compressed
+		let mut encoder = flate2::write::GzEncoder::new(
+			std::fs::File::create(upload.file()).unwrap(),
+			flate2::Compression::default(),
+		);
+		encoder
+			.write_all(package_list(&["a", "b", "c"]).as_bytes())
+			.unwrap();
+		encoder.finish().unwrap();
+		assert_eq!(3, upload.validate().unwrap());
+
+		import_upload(&db, &env, DEFAULT_COMMUNITY, &ImportProgress::default())
+			.await
+			.unwrap();
+		assert_eq!(3, all_mods(&db).await.len());
+
+		let run = latest_run(&db).await;
+		assert_eq!(ImportTrigger::Manual, run.trigger);
+		assert_eq!(ImportSource::Upload, run.source);
+		assert_eq!(Some(1), run.added);
+	}
+
+	#[test]
+	fn invalid_upload_is_rejected() {
+		let (env, _cache_dir) = download_env(String::new());
+		let upload = UploadSource::new(&env, DEFAULT_COMMUNITY);
+
+		std::fs::create_dir_all(upload.file().parent().unwrap()).unwrap();
+		std::fs::write(upload.file(), "{\"not\": \"a package list\"}").unwrap();
+		assert!(upload.validate().is_err());
+	}
+
+	#[test]
+	fn legacy_cache_is_moved_to_default_community() {
+		let (env, _cache_dir) = download_env(String::new());
+		let legacy_file = env.cache_dir.join("mods_cache.json");
+
+		std::fs::write(&legacy_file, package_list(&["a"])).unwrap();
+		move_legacy_cache(&env).unwrap();
+		assert!(!legacy_file.exists());
+		assert_eq!(
+			package_list(&["a"]),
+			std::fs::read_to_string(cache_file(&env)).unwrap()
+		);
+
+		// an existing cache of the community isn't replaced
+		std::fs::write(&legacy_file, package_list(&["b"])).unwrap();
+		move_legacy_cache(&env).unwrap();
+		assert_eq!(
+			package_list(&["a"]),
+			std::fs::read_to_string(cache_file(&env)).unwrap()
+		);
+	}
+
+	#[sqlx::test]
+	async fn importing_chosen_snapshot_in_cache_only_mode(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let (env, _cache_dir) = download_env(String::new());
+
+		write_cache(&env, &package_list(&["a"]));
+		snapshots::take(
+			&snapshot_dir(&env),
+			&cache_file(&env),
+			datetime!(2025-01-01 00:00 UTC),
+			5,
+		)
+		.unwrap();
+		write_cache(&env, &package_list(&["a", "b"]));
+
+		let env = Env {
+			mod_refresh_options: ModRefreshOptions::CacheOnly(ImportSchedule::Interval(
+				Duration::from_secs(3600),
+			)),
+			cache_snapshot: Some("20250102T000000Z".to_string()),
+			..env
+		};
+		do_import_mods(&db, &env, ImportTrigger::Manual, &ImportProgress::default())
+			.await
+			.unwrap();
+
+		assert_eq!(1, all_mods(&db).await.len());
+		assert_eq!(ImportSource::Snapshot, latest_run(&db).await.source);
+	}
+
+	#[sqlx::test]
+	async fn saving_mod_details(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let env = test_env();
+
+		let mut newest = version_value("1.1.0", 10);
+		newest["file_size"] = json!(2000);
+		newest["website_url"] = json!("https://example.com");
+
+		let mut value = mod_value("a", 1, "2025-02-02T12:34:56.000000+00:00");
+		value["donation_link"] = json!("https://ko-fi.com/owner");
+		value["is_pinned"] = json!(true);
+		value["versions"] = json!([newest, version_value("1.0.0", 5)]);
+		let mods = vec![serde_json::from_value(value).unwrap()];
+
+		let mut tx = db.begin_import(ImportProgress::default()).await.unwrap();
+		save_mods_to_db(
+			&mut tx,
+			&mods,
+			DEFAULT_COMMUNITY,
+			&mut HashMap::new(),
+			&mut Vec::new(),
+			&env,
+		)
+		.await
+		.unwrap();
+		tx.commit().await.unwrap();
+
+		let modd = all_mods(&db).await.remove(0);
+		assert_eq!(datetime!(2025-02-02 12:34:56 UTC), modd.updated_date);
+		assert_eq!(datetime!(2025-01-01 10:00 UTC), modd.date_created);
+		assert_eq!(
+			Some("https://ko-fi.com/owner"),
+			modd.donation_link.as_deref()
+		);
+		assert!(modd.pinned);
+		assert_eq!(Some("https://example.com"), modd.website_url.as_deref());
+		assert_eq!(2000, modd.file_size);
+		assert_eq!(15, modd.downloads);
+	}
+
+	#[sqlx::test]
+	async fn unchanged_mods_get_new_download_counts(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool.clone());
+		let env = test_env();
+
+		let raw = |downloads: i64, rating: i64| -> ModRaw {
+			let mut version = version_value("1.0.0", 0);
+			version["downloads"] = json!(downloads);
+			let mut value = mod_value("a", 1, "2025-01-01T10:00:00.000000+00:00");
+			value["rating_score"] = json!(rating);
+			value["versions"] = json!([version]);
+			serde_json::from_value(value).unwrap()
+		};
+
+		let mut tx = db.begin_import(ImportProgress::default()).await.unwrap();
+		let mut categories = HashMap::new();
+		for mods in [vec![raw(10, 1)], vec![raw(25, 3)]] {
+			save_mods_to_db(
+				&mut tx,
+				&mods,
+				DEFAULT_COMMUNITY,
+				&mut categories,
+				&mut Vec::new(),
+				&env,
+			)
+			.await
+			.unwrap();
+		}
+		tx.commit().await.unwrap();
+
+		let modd = all_mods(&db).await.remove(0);
+		assert_eq!(25, modd.downloads);
+		let rating = sqlx::query_scalar::<_, i32>("SELECT rating FROM mods WHERE id = $1")
+			.bind(modd.id)
+			.fetch_one(&pool)
+			.await
+			.unwrap();
+		assert_eq!(3, rating);
+
+		let versions = db.get_mod_versions(&modd.id).await.unwrap();
+		assert_eq!(25, versions[0].downloads);
+	}
+
+	#[sqlx::test]
+	async fn recording_faulty_entries(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let (env, _cache_dir) = download_env(String::new());
+		let env = Env {
+			mod_refresh_options: ModRefreshOptions::CacheOnly(ImportSchedule::Interval(
+				Duration::from_secs(3600),
+			)),
+			..env
+		};
+
+		let date = "2025-01-01T10:00:00.000000+00:00";
+		let mut bad_id = mod_value("bad-id", 2, date);
+		bad_id["uuid4"] = json!("not an uuid");
+		let bad_date = mod_value("bad-date", 3, "yesterday");
+		let package_list = json!([mod_value("no-versions", 1, date), bad_id, bad_date]);
+		write_cache(&env, &package_list.to_string());
+
+		do_import_mods(&db, &env, ImportTrigger::Manual, &ImportProgress::default())
+			.await
+			.unwrap();
+
+		let run = latest_run(&db).await;
+		assert_eq!(Some(2), run.failed);
+		assert_eq!(3, run.problems);
+
+		let problems = db
+			.get_import_problems(run.id, 10)
+			.await
+			.unwrap()
+			.into_iter()
+			.map(|problem| (problem.mod_name, problem.mod_id, problem.kind))
+			.collect::<Vec<_>>();
+		let expected = vec![
+			(
+				"no-versions".to_string(),
+				"00000000-0000-0000-0000-000000000001".to_string(),
+				ImportProblemKind::NoVersions,
+			),
+			(
+				"bad-id".to_string(),
+				"not an uuid".to_string(),
+				ImportProblemKind::InvalidId,
+			),
+			(
+				"bad-date".to_string(),
+				"00000000-0000-0000-0000-000000000003".to_string(),
+				ImportProblemKind::InvalidDate,
+			),
+		];
+		assert_eq!(expected, problems);
+	}
+
+	#[sqlx::test]
+	async fn failed_import_is_rolled_back(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let (env, _cache_dir) = download_env(String::new());
+		let env = Env {
+			mod_refresh_options: ModRefreshOptions::CacheOnly(ImportSchedule::Interval(
+				Duration::from_secs(3600),
+			)),
+			..env
+		};
+
+		write_cache(&env, &package_list(&["a", "b"]));
+		do_import_mods(&db, &env, ImportTrigger::Manual, &ImportProgress::default())
+			.await
+			.unwrap();
+
+		// the first chunks are saved before the list turns out to be broken
+		let old_date = "2025-01-01T10:00:00.000000+00:00";
+		let new_date = "2025-02-02T10:00:00.000000+00:00";
+		let mut updated = mod_value("a", 0, new_date);
+		updated["categories"] = json!([]);
+		let broken = format!(
+			"[{updated}, {}, {}, {{\"name\": ",
+			mod_value("c", 2, old_date),
+			mod_value("d", 3, old_date)
+		);
+		write_cache(&env, &broken);
+
+		let result =
+			do_import_mods(&db, &env, ImportTrigger::Manual, &ImportProgress::default()).await;
+		assert!(result.is_err());
+
+		// mod 'a' keeps its category, and no new mods show up
+		let options = crate::db::ModQueryOptions {
+			ignored_categories: HashSet::from(["Misc".to_string()]),
+			limit: 100,
+			..Default::default()
+		};
+		assert!(db.get_mods(&options, 0).await.unwrap().is_empty());
+		assert_eq!(2, all_mods(&db).await.len());
+	}
+
+	#[sqlx::test]
+	async fn cancelled_import_is_not_saved(pool: Pool<Postgres>) {
+		let db = Database::from_pool(pool);
+		let server = serve_always(json_response(&package_list(&["a", "b", "c"])));
+		let (env, _cache_dir) = download_env(server.url.clone());
+
+		let progress = ImportProgress::default();
+		progress.start();
+		progress.cancel();
+
+		let result = do_import_mods(&db, &env, ImportTrigger::Manual, &progress).await;
+
+		assert!(result.unwrap_err().to_string().contains("cancelled"));
+		assert!(all_mods(&db).await.is_empty());
+		assert!(server.requests().is_empty());
+
+		let run = latest_run(&db).await;
+		assert_eq!(ImportOutcome::Failure, run.outcome);
+	}
 }
@@ -0,0 +1,322 @@
+use std::{collections::HashSet, error::Error, fs::File, io::Write, path::PathBuf, time::Duration};
+
+use async_curl::{Actor, CurlActor};
+use curl::easy::{Easy2, Handler, WriteError};
+use futures_util::future::join_all;
+use sha2::{Digest, Sha256};
+
+use super::{ImportPhase, ImportProgress};
+use crate::{db::Database, env::Env};
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+/// icons fetched at the same time
+const CONCURRENT_FETCHES: usize = 8;
+/// content types of the icons that are cached, and the extensions they're stored with
+const IMAGE_TYPES: [(&str, &str); 4] = [
+	("image/png", "png"),
+	("image/jpeg", "jpg"),
+	("image/webp", "webp"),
+	("image/gif", "gif"),
+];
+
+/// Local copies of mod icons. Icons are stored by the hash of their content, so mods sharing
+/// an icon share the file and a changed icon gets a new file.
+#[derive(Debug, Clone)]
+pub struct IconStore {
+	dir: PathBuf,
+	max_size: usize,
+}
+
+impl IconStore {
+	pub fn new(env: &Env) -> Self {
+		Self {
+			dir: env.cache_dir.join("icons"),
+			max_size: env.icon_max_size,
+		}
+	}
+
+	pub fn is_enabled(&self) -> bool {
+		self.max_size > 0
+	}
+
+	/// Path of a stored icon file, None if `icon_file` isn't a name given by the store
+	pub fn path(&self, icon_file: &str) -> Option<PathBuf> {
+		let (hash, extension) = icon_file.split_once('.')?;
+		let valid = hash.len() == 64
+			&& hash
+				.bytes()
+				.all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+			&& IMAGE_TYPES.iter().any(|(_, ext)| *ext == extension);
+
+		valid.then(|| self.dir.join(icon_file))
+	}
+
+	/// Store an icon, returns the name of its file
+	fn save(&self, data: &[u8], extension: &str) -> Result<String, std::io::Error> {
+		let hash = format!("{:x}", Sha256::digest(data));
+		let icon_file = format!("{hash}.{extension}");
+		let path = self.dir.join(&icon_file);
+
+		if !path.exists() {
+			std::fs::create_dir_all(&self.dir)?;
+
+			// write to a temporary file first, so a half written icon is never served
+			let partial = self.dir.join(format!("{icon_file}.part"));
+			let mut file = File::create(&partial)?;
+			file.write_all(data)?;
+			file.sync_all()?;
+			std::fs::rename(partial, path)?;
+		}
+
+		Ok(icon_file)
+	}
+
+	/// remove the icon files not in `used`, returns how many were removed
+	fn remove_unused(&self, used: &HashSet<String>) -> Result<usize, std::io::Error> {
+		let entries = match std::fs::read_dir(&self.dir) {
+			Ok(entries) => entries,
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+			Err(err) => return Err(err),
+		};
+
+		let mut removed = 0;
+		for entry in entries {
+			let entry = entry?;
+			let name = entry.file_name().to_string_lossy().to_string();
+
+			if !used.contains(&name) {
+				std::fs::remove_file(entry.path())?;
+				removed += 1;
+			}
+		}
+
+		Ok(removed)
+	}
+}
+
+/// Download the icons of the mods whose icon has changed or hasn't been fetched yet, and
+/// remove the icons no mod uses anymore. A failed download is tried again on the next import,
+/// an icon that is too large or not an image is replaced by the placeholder until its url
+/// changes. If none of the icons fetched at the same time can be downloaded, the icon host
+/// is assumed to be unreachable and the rest are left for the next import.
+pub async fn fetch_icons(
+	db: &Database,
+	env: &Env,
+	progress: &ImportProgress,
+) -> Result<(), Box<dyn Error>> {
+	let store = IconStore::new(env);
+	if !store.is_enabled() {
+		return Ok(());
+	}
+
+	let missing = db.get_mods_without_cached_icon().await?;
+	log::info!("Fetching {} mod icons", missing.len());
+
+	let actor = CurlActor::new();
+	let chunks = missing.len().div_ceil(CONCURRENT_FETCHES);
+	let (mut fetched, mut failed) = (0, 0);
+
+	for (i, chunk) in missing.chunks(CONCURRENT_FETCHES).enumerate() {
+		// the mods are already imported, stopping here only leaves icons for the next import
+		if progress.is_cancelled() {
+			log::info!("Fetching mod icons was cancelled");
+			break;
+		}
+		progress.chunk(ImportPhase::FetchingIcons, i + 1, chunks);
+
+		let downloads = join_all(
+			chunk
+				.iter()
+				.map(|(_, icon_url)| download_icon(&actor, icon_url, store.max_size)),
+		)
+		.await;
+
+		if downloads.iter().all(Result::is_err) {
+			if let Some(Err(err)) = downloads.first() {
+				log::warn!(
+					"Failed to download any icons, leaving the rest for the next import: {err}"
+				);
+			}
+			failed += chunk.len();
+			break;
+		}
+
+		for ((mod_id, icon_url), download) in chunk.iter().zip(downloads) {
+			let icon_file = match download {
+				Ok(Downloaded::Icon { data, extension }) => match store.save(&data, extension) {
+					Ok(icon_file) => Some(icon_file),
+					Err(err) => {
+						log::error!("Failed to store icon '{icon_url}': {err}");
+						failed += 1;
+						continue;
+					}
+				},
+				Ok(Downloaded::Rejected(reason)) => {
+					log::warn!("Not caching icon '{icon_url}': {reason}");
+					None
+				}
+				Err(err) => {
+					log::warn!("Failed to download icon '{icon_url}': {err}");
+					failed += 1;
+					continue;
+				}
+			};
+
+			db.set_cached_icon(mod_id, icon_url, icon_file.as_deref())
+				.await?;
+			fetched += 1;
+		}
+	}
+
+	let used = db.get_icon_files().await?;
+	let removed = store.remove_unused(&used)?;
+	log::info!("Fetched {fetched} mod icons, {failed} failed, removed {removed} unused icons");
+
+	Ok(())
+}
+
+enum Downloaded {
+	Icon {
+		data: Vec<u8>,
+		extension: &'static str,
+	},
+	/// the icon can't be cached, downloading it again won't help
+	Rejected(String),
+}
+
+async fn download_icon(
+	actor: &CurlActor<IconHandler>,
+	url: &str,
+	max_size: usize,
+) -> Result<Downloaded, String> {
+	let mut easy = Easy2::new(IconHandler {
+		data: Vec::new(),
+		max_size,
+	});
+	let setup = (|| {
+		easy.url(url)?;
+		easy.get(true)?;
+		easy.follow_location(true)?;
+		easy.timeout(FETCH_TIMEOUT)?;
+		easy.max_filesize(max_size as u64)
+	})();
+	setup.map_err(|err| err.to_string())?;
+
+	let mut easy = match actor.send_request(easy).await {
+		Ok(easy) => easy,
+		// the handler stops writing when the icon grows too large
+		Err(async_curl::Error::Curl(err)) if err.is_filesize_exceeded() || err.is_write_error() => {
+			return Ok(Downloaded::Rejected(format!(
+				"larger than {max_size} bytes"
+			)));
+		}
+		Err(err) => return Err(err.to_string()),
+	};
+
+	let status = easy.response_code().map_err(|err| err.to_string())?;
+	match status {
+		200 => {}
+		404 | 410 => return Ok(Downloaded::Rejected(format!("HTTP status {status}"))),
+		_ => return Err(format!("HTTP status {status}")),
+	}
+
+	let content_type = easy
+		.content_type()
+		.map_err(|err| err.to_string())?
+		.unwrap_or_default()
+		.to_string();
+
+	let Some(extension) = image_extension(&content_type) else {
+		return Ok(Downloaded::Rejected(format!(
+			"unexpected content type '{content_type}'"
+		)));
+	};
+
+	let data = std::mem::take(&mut easy.get_mut().data);
+	Ok(Downloaded::Icon { data, extension })
+}
+
+/// extension of the icons of `content_type`, None if it isn't an image that is cached
+fn image_extension(content_type: &str) -> Option<&'static str> {
+	let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+
+	IMAGE_TYPES
+		.iter()
+		.find(|(image_type, _)| *image_type == mime)
+		.map(|(_, extension)| *extension)
+}
+
+/// Collects the icon into memory, aborting once it grows over `max_size`
+#[derive(Debug)]
+struct IconHandler {
+	data: Vec<u8>,
+	max_size: usize,
+}
+
+impl Handler for IconHandler {
+	fn write(&mut self, data: &[u8]) -> Result<usize, WriteError> {
+		if self.data.len() + data.len() > self.max_size {
+			// writing less than given aborts the transfer
+			return Ok(0);
+		}
+
+		self.data.extend_from_slice(data);
+		Ok(data.len())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use tempfile::TempDir;
+
+	use super::*;
+
+	fn store(dir: &TempDir) -> IconStore {
+		IconStore {
+			dir: dir.path().to_path_buf(),
+			max_size: 100,
+		}
+	}
+
+	#[test]
+	fn icons_are_stored_by_content() {
+		let temp_dir = tempfile::tempdir().unwrap();
+		let store = store(&temp_dir);
+
+		let first = store.save(b"icon", "png").unwrap();
+		let same = store.save(b"icon", "png").unwrap();
+		let other = store.save(b"other icon", "png").unwrap();
+		assert_eq!(first, same);
+		assert_ne!(first, other);
+		assert_eq!(
+			b"icon".to_vec(),
+			std::fs::read(store.path(&first).unwrap()).unwrap()
+		);
+
+		let removed = store
+			.remove_unused(&HashSet::from([other.clone()]))
+			.unwrap();
+		assert_eq!(1, removed);
+		assert!(!store.path(&first).unwrap().exists());
+		assert!(store.path(&other).unwrap().exists());
+	}
+
+	#[test]
+	fn only_stored_icon_files_have_a_path() {
+		let temp_dir = tempfile::tempdir().unwrap();
+		let store = store(&temp_dir);
+		let hash = "a".repeat(64);
+
+		assert!(store.path(&format!("{hash}.png")).is_some());
+		assert!(store.path(&format!("{hash}.exe")).is_none());
+		assert!(store.path("../../etc/passwd").is_none());
+		assert!(store.path(&format!("../{}.png", "a".repeat(61))).is_none());
+	}
+
+	#[test]
+	fn image_extensions() {
+		assert_eq!(Some("png"), image_extension("image/png"));
+		assert_eq!(Some("jpg"), image_extension("Image/JPEG; charset=binary"));
+		assert_eq!(None, image_extension("text/html"));
+	}
+}
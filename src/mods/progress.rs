@@ -0,0 +1,200 @@
+use std::{
+	error::Error,
+	sync::{
+		Arc, Mutex,
+		atomic::{AtomicBool, Ordering},
+	},
+	time::Instant,
+};
+
+/// What a running import is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportPhase {
+	Starting,
+	Downloading,
+	Parsing,
+	InsertingCategories,
+	InsertingMods,
+	InsertingVersions,
+	InsertingDependencies,
+	InsertingCategoryJunctions,
+	Delisting,
+	Committing,
+	/// the mods are imported, their icons are being downloaded
+	FetchingIcons,
+}
+
+/// Progress of a running import at one point in time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgressReport {
+	pub phase: ImportPhase,
+	/// community whose mods are being imported
+	pub community: Option<String>,
+	/// chunk being inserted in the current phase and how many chunks the phase has, 0 if the
+	/// phase isn't done in chunks
+	pub chunk: usize,
+	pub chunks: usize,
+	/// mods of the community handed to the db so far
+	pub mods_processed: usize,
+	pub bytes_downloaded: u64,
+	/// size of the package list being downloaded, if the server told it
+	pub bytes_total: Option<u64>,
+	pub elapsed_secs: u64,
+	pub cancel_requested: bool,
+}
+
+#[derive(Debug)]
+struct State {
+	started_at: Instant,
+	report: ProgressReport,
+}
+
+/// Shared handle for following and cancelling the running import. Clones refer to the same
+/// import. Progress is only tracked between `start` and `finish`, but an import can be cancelled
+/// regardless.
+#[derive(Debug, Clone, Default)]
+pub struct ImportProgress {
+	state: Arc<Mutex<Option<State>>>,
+	cancelled: Arc<AtomicBool>,
+}
+
+impl ImportProgress {
+	pub fn start(&self) {
+		self.cancelled.store(false, Ordering::Relaxed);
+		*self.state.lock().unwrap() = Some(State {
+			started_at: Instant::now(),
+			report: ProgressReport {
+				phase: ImportPhase::Starting,
+				community: None,
+				chunk: 0,
+				chunks: 0,
+				mods_processed: 0,
+				bytes_downloaded: 0,
+				bytes_total: None,
+				elapsed_secs: 0,
+				cancel_requested: false,
+			},
+		});
+	}
+
+	pub fn finish(&self) {
+		*self.state.lock().unwrap() = None;
+		self.cancelled.store(false, Ordering::Relaxed);
+	}
+
+	/// current progress, None if no import is running
+	pub fn report(&self) -> Option<ProgressReport> {
+		self.state
+			.lock()
+			.unwrap()
+			.as_ref()
+			.map(|state| ProgressReport {
+				elapsed_secs: state.started_at.elapsed().as_secs(),
+				cancel_requested: self.is_cancelled(),
+				..state.report.clone()
+			})
+	}
+
+	fn update(&self, f: impl FnOnce(&mut ProgressReport)) {
+		if let Some(state) = self.state.lock().unwrap().as_mut() {
+			f(&mut state.report);
+		}
+	}
+
+	/// the import moves on to the mods of `community`
+	pub fn importing(&self, community: &str) {
+		self.update(|report| {
+			report.community = Some(community.to_string());
+			report.mods_processed = 0;
+			report.bytes_downloaded = 0;
+			report.bytes_total = None;
+		});
+	}
+
+	pub fn phase(&self, phase: ImportPhase) {
+		self.chunk(phase, 0, 0);
+	}
+
+	/// `chunk` is 1-based
+	pub fn chunk(&self, phase: ImportPhase, chunk: usize, chunks: usize) {
+		self.update(|report| {
+			report.phase = phase;
+			report.chunk = chunk;
+			report.chunks = chunks;
+		});
+	}
+
+	pub fn mods_processed(&self, count: usize) {
+		self.update(|report| report.mods_processed += count);
+	}
+
+	/// a download begins, `bytes` of it might already be downloaded if it's resumed
+	pub fn download_started(&self, bytes: u64, bytes_total: Option<u64>) {
+		self.update(|report| {
+			report.bytes_downloaded = bytes;
+			report.bytes_total = bytes_total;
+		});
+	}
+
+	pub fn downloaded(&self, bytes: u64) {
+		self.update(|report| report.bytes_downloaded += bytes);
+	}
+
+	/// Ask the running import to stop. Nothing imported so far is kept, the import is stopped
+	/// at the next chunk.
+	pub fn cancel(&self) {
+		self.cancelled.store(true, Ordering::Relaxed);
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.cancelled.load(Ordering::Relaxed)
+	}
+
+	/// error out if the import has been cancelled
+	pub fn check_cancelled(&self) -> Result<(), Box<dyn Error>> {
+		if self.is_cancelled() {
+			return Err("Import was cancelled".into());
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn progress_is_reported_only_while_running() {
+		let progress = ImportProgress::default();
+		assert_eq!(None, progress.report());
+
+		progress.start();
+		progress.importing("lethal-company");
+		progress.phase(ImportPhase::Parsing);
+		progress.mods_processed(10);
+		progress.chunk(ImportPhase::InsertingMods, 2, 5);
+
+		let report = progress.report().unwrap();
+		assert_eq!(ImportPhase::InsertingMods, report.phase);
+		assert_eq!(Some("lethal-company"), report.community.as_deref());
+		assert_eq!((2, 5), (report.chunk, report.chunks));
+		assert_eq!(10, report.mods_processed);
+
+		progress.finish();
+		assert_eq!(None, progress.report());
+	}
+
+	#[test]
+	fn cancelling_is_reset_by_next_import() {
+		let progress = ImportProgress::default();
+
+		progress.start();
+		progress.clone().cancel();
+		assert!(progress.report().unwrap().cancel_requested);
+		assert!(progress.check_cancelled().is_err());
+
+		progress.start();
+		assert!(progress.check_cancelled().is_ok());
+	}
+}
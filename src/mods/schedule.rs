@@ -0,0 +1,318 @@
+use std::{
+	fmt::{self, Display},
+	time::Duration,
+};
+
+use time::{Date, OffsetDateTime, PrimitiveDateTime, Time, macros::format_description};
+use time_tz::{OffsetDateTimeExt, OffsetResult, PrimitiveDateTimeExt, TimeZone, Tz, timezones};
+
+const MONTH_NAMES: [&str; 12] = [
+	"jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// how far ahead the next run is looked for, long enough for schedules running only on Feb 29
+const SEARCH_DAYS: usize = 8 * 366;
+
+/// When mods are imported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportSchedule {
+	/// once this much time has passed since the previous import
+	Interval(Duration),
+	/// at the times of day matching a cron expression
+	Cron(CronSchedule),
+}
+
+impl ImportSchedule {
+	/// When the import following the one at `last_import` is due. None if the schedule never
+	/// runs again.
+	pub fn next_import(&self, last_import: OffsetDateTime) -> Option<OffsetDateTime> {
+		match self {
+			ImportSchedule::Interval(interval) => Some(last_import + *interval),
+			ImportSchedule::Cron(schedule) => schedule.next_after(last_import),
+		}
+	}
+}
+
+/// Cron style schedule, e.g. `0 5 * * *` for daily at 05:00. The fields are minute, hour,
+/// day of month, month and day of week, each either `*`, a number, a range such as `1-5` or a
+/// list of them separated by commas, optionally followed by a step such as `*/15`. Months and
+/// days of week can also be written as three letter names, e.g. `mon,wed,fri`. Times are in
+/// `timezone`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+	expression: String,
+	timezone: &'static Tz,
+	/// bit n is set if the field matches n
+	minutes: u64,
+	hours: u64,
+	days: u64,
+	months: u64,
+	/// Sunday is 0
+	weekdays: u64,
+	/// when both day fields are restricted, i.e. don't match every value, a day matching either
+	/// of them matches
+	days_restricted: bool,
+	weekdays_restricted: bool,
+}
+
+impl CronSchedule {
+	pub fn parse(expression: &str, timezone: &str) -> Result<Self, String> {
+		let timezone = timezones::get_by_name(timezone)
+			.ok_or_else(|| format!("Unknown timezone '{timezone}'"))?;
+
+		let fields = expression.split_whitespace().collect::<Vec<_>>();
+		let [minutes, hours, days, months, weekdays] = fields[..] else {
+			return Err(format!(
+				"Expected 5 fields (minute hour day month weekday) in '{expression}'"
+			));
+		};
+
+		let days = parse_field(days, 1, 31, &[])?;
+		let mut weekdays = parse_field(weekdays, 0, 7, &WEEKDAY_NAMES)?;
+		// both 0 and 7 are Sunday
+		if weekdays & (1 << 7) != 0 {
+			weekdays = (weekdays | 1) & !(1 << 7);
+		}
+
+		Ok(Self {
+			expression: fields.join(" "),
+			timezone,
+			minutes: parse_field(minutes, 0, 59, &[])?,
+			hours: parse_field(hours, 0, 23, &[])?,
+			days,
+			months: parse_field(months, 1, 12, &MONTH_NAMES)?,
+			weekdays,
+			days_restricted: days != all_values(1, 31),
+			weekdays_restricted: weekdays != all_values(0, 6),
+		})
+	}
+
+	/// first scheduled time after `time`
+	pub fn next_after(&self, time: OffsetDateTime) -> Option<OffsetDateTime> {
+		let mut date = time.to_timezone(self.timezone).date();
+
+		for _ in 0..SEARCH_DAYS {
+			if self.matches_day(date) {
+				let next = self
+					.times_of_day()
+					.filter_map(|time_of_day| {
+						self.resolve(PrimitiveDateTime::new(date, time_of_day))
+					})
+					.find(|scheduled| *scheduled > time);
+
+				if next.is_some() {
+					return next;
+				}
+			}
+
+			date = date.next_day()?;
+		}
+
+		None
+	}
+
+	/// `time` formatted in the timezone of the schedule
+	pub fn format(&self, time: OffsetDateTime) -> String {
+		let local = time
+			.to_timezone(self.timezone)
+			.format(format_description!("[year]-[month]-[day] [hour]:[minute]"))
+			.unwrap_or_else(|_| "-".to_string());
+
+		format!("{local} {}", self.timezone.name())
+	}
+
+	fn matches_day(&self, date: Date) -> bool {
+		let day = bit(self.days, date.day());
+		let weekday = bit(self.weekdays, date.weekday().number_days_from_sunday());
+
+		let day_matches = match (self.days_restricted, self.weekdays_restricted) {
+			(true, true) => day || weekday,
+			_ => day && weekday,
+		};
+
+		day_matches && bit(self.months, date.month() as u8)
+	}
+
+	fn times_of_day(&self) -> impl Iterator<Item = Time> {
+		(0..24u8)
+			.filter(|hour| bit(self.hours, *hour))
+			.flat_map(|hour| {
+				(0..60u8)
+					.filter(|minute| bit(self.minutes, *minute))
+					.filter_map(move |minute| Time::from_hms(hour, minute, 0).ok())
+			})
+	}
+
+	/// Local time to an actual point in time. Times repeated when clocks are turned back run
+	/// only the first time, and times skipped when clocks are turned forward run an hour later.
+	fn resolve(&self, local: PrimitiveDateTime) -> Option<OffsetDateTime> {
+		match local.assume_timezone(self.timezone) {
+			OffsetResult::Some(time) | OffsetResult::Ambiguous(time, _) => Some(time),
+			OffsetResult::None => {
+				let before_skip = local - time::Duration::HOUR;
+				match before_skip.assume_timezone(self.timezone) {
+					OffsetResult::Some(time) | OffsetResult::Ambiguous(time, _) => {
+						Some(time + time::Duration::HOUR)
+					}
+					OffsetResult::None => None,
+				}
+			}
+		}
+	}
+}
+
+impl Display for CronSchedule {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} {}", self.expression, self.timezone.name())
+	}
+}
+
+fn bit(bits: u64, n: u8) -> bool {
+	bits & (1 << n) != 0
+}
+
+/// bit set matching every value from `min` to `max`
+fn all_values(min: u8, max: u8) -> u64 {
+	(min..=max).fold(0, |bits, n| bits | 1 << n)
+}
+
+/// Parse one field of a cron expression into a bit set of the values it matches.
+/// `names` are alternatives for the values starting from `min`.
+fn parse_field(field: &str, min: u8, max: u8, names: &[&str]) -> Result<u64, String> {
+	let value = |str: &str| -> Result<u8, String> {
+		let lowercase = str.to_ascii_lowercase();
+		let value = match names.iter().position(|name| *name == lowercase) {
+			Some(index) => index as u8 + min,
+			None => str
+				.parse()
+				.map_err(|_| format!("Not a valid value: '{str}'"))?,
+		};
+
+		if value < min || value > max {
+			return Err(format!("{value} is not between {min} and {max}"));
+		}
+
+		Ok(value)
+	};
+
+	let mut bits = 0;
+	for item in field.split(',') {
+		let (range, step) = match item.split_once('/') {
+			Some((range, step)) => {
+				let step = step
+					.parse::<u8>()
+					.ok()
+					.filter(|step| *step > 0)
+					.ok_or_else(|| format!("Not a valid step: '{step}'"))?;
+				(range, step)
+			}
+			None => (item, 1),
+		};
+
+		let (start, end) = match range.split_once('-') {
+			_ if range == "*" => (min, max),
+			Some((start, end)) => (value(start)?, value(end)?),
+			// a single value with a step runs from the value to the end of the range
+			None if step > 1 => (value(range)?, max),
+			None => (value(range)?, value(range)?),
+		};
+
+		if start > end {
+			return Err(format!("Not a valid range: '{range}'"));
+		}
+
+		for n in (start..=end).step_by(step as usize) {
+			bits |= 1 << n;
+		}
+	}
+
+	Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+	use time::macros::datetime;
+
+	use super::*;
+
+	#[test]
+	fn next_daily_run_in_timezone() {
+		let schedule = CronSchedule::parse("0 5 * * *", "Europe/Helsinki").unwrap();
+
+		// 05:00 in Helsinki is 02:00 UTC in winter and 03:00 UTC in summer
+		let next = schedule.next_after(datetime!(2025-01-10 12:00 UTC));
+		assert_eq!(Some(datetime!(2025-01-11 03:00 UTC)), next);
+
+		let next = schedule.next_after(datetime!(2025-07-10 01:00 UTC));
+		assert_eq!(Some(datetime!(2025-07-10 02:00 UTC)), next);
+
+		// exactly at the scheduled time, the next run is the day after
+		let next = schedule.next_after(datetime!(2025-07-10 02:00 UTC));
+		assert_eq!(Some(datetime!(2025-07-11 02:00 UTC)), next);
+	}
+
+	#[test]
+	fn next_run_on_chosen_weekdays() {
+		let schedule = CronSchedule::parse("30 4 * * mon,wed,fri", "UTC").unwrap();
+
+		// 2025-03-06 is a Thursday
+		let next = schedule.next_after(datetime!(2025-03-06 12:00 UTC));
+		assert_eq!(Some(datetime!(2025-03-07 04:30 UTC)), next);
+
+		let next = schedule.next_after(datetime!(2025-03-07 12:00 UTC));
+		assert_eq!(Some(datetime!(2025-03-10 04:30 UTC)), next);
+	}
+
+	#[test]
+	fn day_fields_matching_every_day_are_not_restrictions() {
+		// every day of the month, so only the weekday restricts the days
+		let schedule = CronSchedule::parse("0 5 1-31 * mon", "UTC").unwrap();
+
+		// 2025-03-06 is a Thursday
+		let next = schedule.next_after(datetime!(2025-03-06 12:00 UTC));
+		assert_eq!(Some(datetime!(2025-03-10 05:00 UTC)), next);
+
+		// every other day of the month or Mondays
+		let schedule = CronSchedule::parse("0 5 */2 * mon", "UTC").unwrap();
+		let next = schedule.next_after(datetime!(2025-03-06 12:00 UTC));
+		assert_eq!(Some(datetime!(2025-03-07 05:00 UTC)), next);
+
+		let schedule = CronSchedule::parse("0 5 */2 * 0-7", "UTC").unwrap();
+		let next = schedule.next_after(datetime!(2025-03-07 12:00 UTC));
+		assert_eq!(Some(datetime!(2025-03-09 05:00 UTC)), next);
+	}
+
+	#[test]
+	fn time_skipped_by_daylight_saving_runs_an_hour_later() {
+		// clocks in Helsinki jumped from 03:00 to 04:00 on 2025-03-30
+		let schedule = CronSchedule::parse("30 3 * * *", "Europe/Helsinki").unwrap();
+
+		// 04:30 in summer time
+		let next = schedule.next_after(datetime!(2025-03-29 12:00 UTC));
+		assert_eq!(Some(datetime!(2025-03-30 01:30 UTC)), next);
+	}
+
+	#[test]
+	fn parsing_fields() {
+		assert_eq!(Ok(0b1111), parse_field("*", 0, 3, &[]));
+		assert_eq!(Ok(0b1010_1010), parse_field("1-7/2", 0, 7, &[]));
+		assert_eq!(Ok(0b1000_0001), parse_field("0,7", 0, 7, &[]));
+		assert_eq!(Ok(1 << 12), parse_field("dec", 1, 12, &MONTH_NAMES));
+		assert!(parse_field("60", 0, 59, &[]).is_err());
+		assert!(parse_field("5-1", 0, 59, &[]).is_err());
+		assert!(parse_field("*/0", 0, 59, &[]).is_err());
+	}
+
+	#[test]
+	fn parsing_invalid_schedules() {
+		assert!(CronSchedule::parse("0 5 * *", "UTC").is_err());
+		assert!(CronSchedule::parse("0 5 * * *", "Mars/Olympus_Mons").is_err());
+	}
+
+	#[test]
+	fn schedule_that_never_runs() {
+		let schedule = CronSchedule::parse("0 0 31 2 *", "UTC").unwrap();
+		assert_eq!(None, schedule.next_after(datetime!(2025-01-01 00:00 UTC)));
+	}
+}
@@ -0,0 +1,188 @@
+use std::{
+	fs::File,
+	io::{BufReader, BufWriter, Read},
+	path::{Path, PathBuf},
+};
+
+use flate2::{Compression, bufread::GzDecoder, write::GzEncoder};
+use time::{
+	OffsetDateTime, PrimitiveDateTime, format_description::BorrowedFormatItem,
+	macros::format_description,
+};
+
+/// Snapshots are named after the time they were taken, so that sorting the names sorts the
+/// snapshots chronologically. E.g. `20250320T100000Z`
+const NAME_FORMAT: &[BorrowedFormatItem] =
+	format_description!("[year][month][day]T[hour][minute][second]Z");
+const EXTENSION: &str = ".json.gz";
+
+/// A gzip compressed copy of a package list, taken whenever a new package list is downloaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+	pub name: String,
+	pub taken_at: OffsetDateTime,
+	/// compressed size in bytes
+	pub size: u64,
+}
+
+/// time the snapshot called `name` was taken, None if `name` isn't a valid snapshot name
+pub fn parse_name(name: &str) -> Option<OffsetDateTime> {
+	PrimitiveDateTime::parse(name, NAME_FORMAT)
+		.ok()
+		.map(|date| date.assume_utc())
+}
+
+fn snapshot_file(dir: &Path, name: &str) -> PathBuf {
+	dir.join(format!("{name}{EXTENSION}"))
+}
+
+/// Compress `package_list` into a new snapshot, then remove the oldest snapshots so that
+/// at most `keep` remain
+pub fn take(
+	dir: &Path,
+	package_list: &Path,
+	taken_at: OffsetDateTime,
+	keep: usize,
+) -> Result<(), std::io::Error> {
+	if keep == 0 {
+		return Ok(());
+	}
+
+	std::fs::create_dir_all(dir)?;
+
+	let name = taken_at
+		.to_offset(time::UtcOffset::UTC)
+		.format(NAME_FORMAT)
+		.map_err(std::io::Error::other)?;
+
+	let mut reader = BufReader::new(File::open(package_list)?);
+	let writer = BufWriter::new(File::create(snapshot_file(dir, &name))?);
+	let mut encoder = GzEncoder::new(writer, Compression::default());
+	std::io::copy(&mut reader, &mut encoder)?;
+	encoder.finish()?.into_inner()?.sync_all()?;
+
+	for old in list(dir)?.into_iter().skip(keep) {
+		log::debug!("Removing old snapshot '{}'", old.name);
+		std::fs::remove_file(snapshot_file(dir, &old.name))?;
+	}
+
+	Ok(())
+}
+
+/// snapshots in `dir`, newest first
+pub fn list(dir: &Path) -> Result<Vec<Snapshot>, std::io::Error> {
+	let entries = match std::fs::read_dir(dir) {
+		Ok(entries) => entries,
+		Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(err) => return Err(err),
+	};
+
+	let mut snapshots = Vec::new();
+	for entry in entries {
+		let entry = entry?;
+		let file_name = entry.file_name();
+		let Some(name) = file_name
+			.to_str()
+			.and_then(|file_name| file_name.strip_suffix(EXTENSION))
+		else {
+			continue;
+		};
+
+		let Some(taken_at) = parse_name(name) else {
+			continue;
+		};
+
+		snapshots.push(Snapshot {
+			name: name.to_string(),
+			taken_at,
+			size: entry.metadata()?.len(),
+		});
+	}
+
+	snapshots.sort_by(|a, b| b.name.cmp(&a.name));
+	Ok(snapshots)
+}
+
+/// Open the newest snapshot taken at or before the time in `name`, returns the decompressed
+/// package list
+pub fn open(dir: &Path, name: &str) -> Result<impl Read + Send + use<>, std::io::Error> {
+	let snapshot = list(dir)?
+		.into_iter()
+		.find(|snapshot| snapshot.name.as_str() <= name)
+		.ok_or_else(|| {
+			std::io::Error::new(
+				std::io::ErrorKind::NotFound,
+				format!(
+					"No snapshot taken at or before '{name}' in {}",
+					dir.display()
+				),
+			)
+		})?;
+
+	log::info!("Reading package list from snapshot '{}'", snapshot.name);
+	let file = File::open(snapshot_file(dir, &snapshot.name))?;
+	Ok(GzDecoder::new(BufReader::new(file)))
+}
+
+#[cfg(test)]
+mod tests {
+	use time::macros::datetime;
+
+	use super::*;
+
+	fn take_snapshot(dir: &Path, content: &str, taken_at: OffsetDateTime, keep: usize) {
+		let package_list = dir.join("mods_cache.json");
+		std::fs::write(&package_list, content).unwrap();
+		take(&dir.join("snapshots"), &package_list, taken_at, keep).unwrap();
+	}
+
+	fn read_snapshot(dir: &Path, name: &str) -> String {
+		let mut content = String::new();
+		open(&dir.join("snapshots"), name)
+			.unwrap()
+			.read_to_string(&mut content)
+			.unwrap();
+		content
+	}
+
+	#[test]
+	fn only_newest_snapshots_are_kept() {
+		let temp_dir = tempfile::tempdir().unwrap();
+		let dir = temp_dir.path();
+
+		take_snapshot(dir, "[1]", datetime!(2025-01-01 10:00 UTC), 2);
+		take_snapshot(dir, "[2]", datetime!(2025-01-02 10:00 UTC), 2);
+		take_snapshot(dir, "[3]", datetime!(2025-01-03 10:00 UTC), 2);
+
+		let names = list(&dir.join("snapshots"))
+			.unwrap()
+			.into_iter()
+			.map(|snapshot| snapshot.name)
+			.collect::<Vec<_>>();
+		assert_eq!(vec!["20250103T100000Z", "20250102T100000Z"], names);
+	}
+
+	#[test]
+	fn opening_newest_snapshot_before_time() {
+		let temp_dir = tempfile::tempdir().unwrap();
+		let dir = temp_dir.path();
+
+		take_snapshot(dir, "[1]", datetime!(2025-01-01 10:00 UTC), 5);
+		take_snapshot(dir, "[2]", datetime!(2025-01-02 10:00 UTC), 5);
+
+		assert_eq!("[1]", read_snapshot(dir, "20250101T100000Z"));
+		assert_eq!("[1]", read_snapshot(dir, "20250102T000000Z"));
+		assert_eq!("[2]", read_snapshot(dir, "20250110T000000Z"));
+		assert!(open(&dir.join("snapshots"), "20241231T000000Z").is_err());
+	}
+
+	#[test]
+	fn no_snapshots_are_taken_if_none_are_kept() {
+		let temp_dir = tempfile::tempdir().unwrap();
+		let dir = temp_dir.path();
+
+		take_snapshot(dir, "[1]", datetime!(2025-01-01 10:00 UTC), 0);
+
+		assert!(list(&dir.join("snapshots")).unwrap().is_empty());
+	}
+}
@@ -0,0 +1,592 @@
+use std::{
+	error::Error,
+	fmt::{self, Display},
+	fs::{File, OpenOptions},
+	io::{BufRead, BufReader, BufWriter, Read, Write},
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use async_curl::{Actor, CurlActor};
+use curl::easy::{Easy2, Handler, List, WriteError};
+use flate2::bufread::GzDecoder;
+use serde::{Deserialize, Serialize};
+
+use time::OffsetDateTime;
+
+use super::{
+	ImportProgress, ModRaw, parse_mods_in_chunks,
+	snapshots::{self, Snapshot},
+};
+use crate::env::Env;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// abort a transfer that stays below `LOW_SPEED_LIMIT` bytes per second for `LOW_SPEED_TIME`
+const LOW_SPEED_LIMIT: u32 = 1024;
+const LOW_SPEED_TIME: Duration = Duration::from_secs(60);
+/// chunk size used when checking that a downloaded package list parses
+const VALIDATION_CHUNK_SIZE: usize = 100;
+
+/// Where the package list of a community comes from
+pub trait ModSource {
+	/// slug of the community the mods belong to, e.g. `lethal-company`
+	fn community(&self) -> &str;
+
+	/// Download the package list into the cache. The previous cache is replaced only
+	/// after the new package list has been downloaded completely and parses.
+	async fn download(&self, progress: &ImportProgress) -> Result<(), Box<dyn Error>>;
+
+	/// open the cached package list for parsing
+	fn open_cache(&self) -> Result<Box<dyn Read + Send>, std::io::Error>;
+
+	/// Parse a package list, calling `on_chunk` with at most `chunk_size` mods at a time.
+	/// Returns the total number of mods parsed.
+	fn parse(
+		&self,
+		reader: impl Read,
+		chunk_size: usize,
+		on_chunk: impl FnMut(Vec<ModRaw>) -> Result<(), String>,
+	) -> Result<usize, Box<dyn Error + Send + Sync>> {
+		Ok(parse_mods_in_chunks(reader, chunk_size, on_chunk)?)
+	}
+}
+
+/// Package list of a single community on Thunderstore
+#[derive(Debug, Clone)]
+pub struct ThunderstoreSource {
+	community: String,
+	base_url: String,
+	cache_dir: PathBuf,
+	retries: u32,
+	retry_delay: Duration,
+	snapshots_kept: usize,
+	/// read the package list from a snapshot instead of the cache, see `at_snapshot`
+	snapshot: Option<String>,
+}
+
+impl ThunderstoreSource {
+	pub fn new(env: &Env, community: &str) -> Self {
+		Self {
+			community: community.to_string(),
+			base_url: env.thunderstore_url.clone(),
+			cache_dir: env.cache_dir.join(community),
+			retries: env.download_retries,
+			retry_delay: env.download_retry_delay,
+			snapshots_kept: env.cache_snapshots,
+			snapshot: None,
+		}
+	}
+
+	/// Read the package list from the newest snapshot taken at or before the time in
+	/// `snapshot` instead of the cache
+	pub fn at_snapshot(self, snapshot: &str) -> Self {
+		Self {
+			snapshot: Some(snapshot.to_string()),
+			..self
+		}
+	}
+
+	/// Move the package list at `file` into the cache, unless the community already has a cached
+	/// package list
+	pub fn adopt_cache(&self, file: &Path) -> Result<(), std::io::Error> {
+		if !file.exists() || self.cache_file().exists() {
+			return Ok(());
+		}
+
+		log::info!(
+			"Moving package list '{}' to '{}'",
+			file.display(),
+			self.cache_file().display()
+		);
+		std::fs::create_dir_all(&self.cache_dir)?;
+		std::fs::rename(file, self.cache_file())
+	}
+
+	/// snapshots of the package list, newest first
+	pub fn snapshots(&self) -> Result<Vec<Snapshot>, std::io::Error> {
+		snapshots::list(&self.snapshot_dir())
+	}
+
+	fn snapshot_dir(&self) -> PathBuf {
+		self.cache_dir.join("snapshots")
+	}
+
+	fn api_url(&self) -> String {
+		format!("{}/c/{}/api/v1/package/", self.base_url, self.community)
+	}
+
+	fn cache_file(&self) -> PathBuf {
+		self.cache_dir.join("mods_cache.json")
+	}
+
+	/// the package list is downloaded here, and moved to the cache once it's complete
+	fn download_file(&self) -> PathBuf {
+		self.cache_dir.join("mods_cache.json.part")
+	}
+
+	/// Download the package list once. A partially downloaded list left behind by an earlier
+	/// attempt is resumed if the server still has the same version of it.
+	async fn try_download(
+		&self,
+		actor: &CurlActor<ResponseHandler>,
+		progress: &ImportProgress,
+	) -> Result<Downloaded, DownloadError> {
+		let download_file = self.download_file();
+		std::fs::create_dir_all(&self.cache_dir)?;
+
+		let mut headers = List::new();
+
+		if self.cache_file().exists() {
+			let cached = Validators::load(&meta_file(&self.cache_file()));
+			if let Some(etag) = &cached.etag {
+				headers.append(&format!("If-None-Match: {etag}"))?;
+			}
+			if let Some(last_modified) = &cached.last_modified {
+				headers.append(&format!("If-Modified-Since: {last_modified}"))?;
+			}
+		}
+
+		let downloaded_bytes = std::fs::metadata(&download_file)
+			.map(|metadata| metadata.len())
+			.unwrap_or(0);
+		let partial = Validators::load(&meta_file(&download_file));
+		let resume = partial.if_range().filter(|_| downloaded_bytes > 0);
+
+		let handler = ResponseHandler::new(
+			download_file.clone(),
+			progress.clone(),
+			resume.map_or(0, |_| downloaded_bytes),
+		);
+		let mut easy = Easy2::new(handler);
+		easy.url(&self.api_url())?;
+		easy.get(true)?;
+		easy.connect_timeout(CONNECT_TIMEOUT)?;
+		easy.low_speed_limit(LOW_SPEED_LIMIT)?;
+		easy.low_speed_time(LOW_SPEED_TIME)?;
+
+		if let Some(validator) = resume {
+			log::info!("Resuming download from byte {downloaded_bytes}");
+			easy.resume_from(downloaded_bytes)?;
+			headers.append(&format!("If-Range: {validator}"))?;
+		}
+
+		easy.http_headers(headers)?;
+
+		log::info!("Starting mods json download for '{}'", self.community);
+		let mut easy = actor
+			.send_request(easy)
+			.await
+			.map_err(DownloadError::retryable)?;
+		easy.get_mut().finish()?;
+
+		let status = easy.response_code()?;
+		match status {
+			200 | 206 => {}
+			304 => return Ok(Downloaded::NotModified),
+			416 => {
+				// the partial download doesn't fit the package list anymore, start over
+				remove_download(&download_file);
+				return Err(DownloadError::retryable(format!("HTTP status {status}")));
+			}
+			408 | 429 | 500..=599 => {
+				return Err(DownloadError::retryable(format!("HTTP status {status}")));
+			}
+			_ => return Err(DownloadError::fatal(format!("HTTP status {status}"))),
+		}
+
+		let content_type = easy.content_type()?.unwrap_or_default();
+		if !is_json(content_type) {
+			return Err(DownloadError::fatal(format!(
+				"Unexpected content type '{content_type}'"
+			)));
+		}
+
+		Ok(Downloaded::Complete)
+	}
+
+	/// Move the downloaded package list to the cache, if it parses
+	async fn replace_cache(&self) -> Result<(), Box<dyn Error>> {
+		let download_file = self.download_file();
+
+		let source = self.clone();
+		let file = download_file.clone();
+		let parsed = actix_rt::task::spawn_blocking(move || {
+			let reader = BufReader::new(File::open(file)?);
+			source.parse(reader, VALIDATION_CHUNK_SIZE, |_| Ok(()))
+		})
+		.await?;
+
+		if let Err(err) = parsed {
+			remove_download(&download_file);
+			return Err(format!(
+				"Downloaded package list is not valid, keeping the previous cache: {err}"
+			)
+			.into());
+		}
+
+		log::debug!("Saving mods json to cache");
+		let cache_file = self.cache_file();
+		std::fs::rename(&download_file, &cache_file)?;
+		if std::fs::rename(meta_file(&download_file), meta_file(&cache_file)).is_err() {
+			// without validators the next download can't be conditional
+			std::fs::remove_file(meta_file(&cache_file)).ok();
+		}
+
+		let snapshot_dir = self.snapshot_dir();
+		let keep = self.snapshots_kept;
+		actix_rt::task::spawn_blocking(move || {
+			snapshots::take(&snapshot_dir, &cache_file, OffsetDateTime::now_utc(), keep)
+		})
+		.await?
+		.inspect_err(|err| log::error!("Failed to take a snapshot of the package list: {err}"))
+		.ok();
+
+		Ok(())
+	}
+}
+
+impl ModSource for ThunderstoreSource {
+	fn community(&self) -> &str {
+		&self.community
+	}
+
+	async fn download(&self, progress: &ImportProgress) -> Result<(), Box<dyn Error>> {
+		let actor = CurlActor::new();
+
+		let mut attempt = 0;
+		loop {
+			progress.check_cancelled()?;
+			let result = self.try_download(&actor, progress).await;
+			// a cancelled transfer fails, don't retry it. The partial download is kept for resuming.
+			progress.check_cancelled()?;
+
+			match result {
+				Ok(Downloaded::Complete) => break,
+				Ok(Downloaded::NotModified) => {
+					log::info!(
+						"Package list of '{}' hasn't changed, using the cached one",
+						self.community
+					);
+					return Ok(());
+				}
+				Err(err) if err.retryable && attempt < self.retries => {
+					let delay = self
+						.retry_delay
+						.saturating_mul(2u32.saturating_pow(attempt));
+					log::warn!("Downloading mods failed, retrying in {delay:?}: {err}");
+
+					actix_rt::time::sleep(delay).await;
+					attempt += 1;
+				}
+				Err(err) => return Err(format!("Downloading mods failed: {err}").into()),
+			}
+		}
+
+		self.replace_cache().await
+	}
+
+	fn open_cache(&self) -> Result<Box<dyn Read + Send>, std::io::Error> {
+		match &self.snapshot {
+			Some(snapshot) => Ok(Box::new(snapshots::open(&self.snapshot_dir(), snapshot)?)),
+			None => Ok(Box::new(BufReader::new(File::open(self.cache_file())?))),
+		}
+	}
+}
+
+/// Package list of a community uploaded by an admin, for hosts that can't reach Thunderstore
+#[derive(Debug, Clone)]
+pub struct UploadSource {
+	community: String,
+	file: PathBuf,
+}
+
+impl UploadSource {
+	pub fn new(env: &Env, community: &str) -> Self {
+		Self {
+			community: community.to_string(),
+			file: env.cache_dir.join(community).join("upload.json"),
+		}
+	}
+
+	/// package list at `file`, e.g. an upload that hasn't been checked yet
+	pub fn from_file(community: &str, file: &Path) -> Self {
+		Self {
+			community: community.to_string(),
+			file: file.to_path_buf(),
+		}
+	}
+
+	/// where the uploaded package list is kept until it's imported, either as is or gzip
+	/// compressed
+	pub fn file(&self) -> &Path {
+		&self.file
+	}
+
+	/// Check that the uploaded package list parses, returns the number of mods in it
+	pub fn validate(&self) -> Result<usize, Box<dyn Error + Send + Sync>> {
+		self.parse(self.open_cache()?, VALIDATION_CHUNK_SIZE, |_| Ok(()))
+	}
+
+	pub fn remove(&self) {
+		std::fs::remove_file(&self.file)
+			.inspect_err(|err| log::warn!("Failed to remove uploaded package list: {err}"))
+			.ok();
+	}
+}
+
+impl ModSource for UploadSource {
+	fn community(&self) -> &str {
+		&self.community
+	}
+
+	async fn download(&self, _progress: &ImportProgress) -> Result<(), Box<dyn Error>> {
+		// already uploaded
+		Ok(())
+	}
+
+	fn open_cache(&self) -> Result<Box<dyn Read + Send>, std::io::Error> {
+		let mut reader = BufReader::new(File::open(&self.file)?);
+
+		if is_gzip(reader.fill_buf()?) {
+			Ok(Box::new(GzDecoder::new(reader)))
+		} else {
+			Ok(Box::new(reader))
+		}
+	}
+}
+
+fn is_gzip(data: &[u8]) -> bool {
+	data.starts_with(&[0x1f, 0x8b])
+}
+
+enum Downloaded {
+	Complete,
+	/// the cached package list is still up to date
+	NotModified,
+}
+
+/// Error of a single download attempt
+#[derive(Debug)]
+struct DownloadError {
+	message: String,
+	/// whether trying again could succeed, e.g. after a timeout or a 503 response
+	retryable: bool,
+}
+
+impl DownloadError {
+	fn retryable(err: impl Display) -> Self {
+		Self {
+			message: err.to_string(),
+			retryable: true,
+		}
+	}
+
+	fn fatal(err: impl Display) -> Self {
+		Self {
+			message: err.to_string(),
+			retryable: false,
+		}
+	}
+}
+
+impl Display for DownloadError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.message)
+	}
+}
+
+impl Error for DownloadError {}
+
+impl From<curl::Error> for DownloadError {
+	fn from(err: curl::Error) -> Self {
+		Self::fatal(err)
+	}
+}
+
+impl From<std::io::Error> for DownloadError {
+	fn from(err: std::io::Error) -> Self {
+		Self::fatal(err)
+	}
+}
+
+/// HTTP validators of a downloaded package list, stored next to it. They make it possible
+/// to skip downloading an unchanged list and to resume an interrupted download.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct Validators {
+	etag: Option<String>,
+	last_modified: Option<String>,
+}
+
+impl Validators {
+	/// validators saved at `path`, or none if there's nothing readable saved
+	fn load(path: &Path) -> Self {
+		std::fs::read(path)
+			.ok()
+			.and_then(|bytes| serde_json::from_slice(&bytes).ok())
+			.unwrap_or_default()
+	}
+
+	fn save(&self, path: &Path) -> Result<(), std::io::Error> {
+		std::fs::write(path, serde_json::to_vec(self)?)
+	}
+
+	/// value for the `If-Range` header, which doesn't accept weak etags
+	fn if_range(&self) -> Option<&str> {
+		self.etag
+			.as_deref()
+			.filter(|etag| !etag.starts_with("W/"))
+			.or(self.last_modified.as_deref())
+	}
+}
+
+fn meta_file(path: &Path) -> PathBuf {
+	let mut file = path.as_os_str().to_owned();
+	file.push(".meta");
+	PathBuf::from(file)
+}
+
+fn remove_download(download_file: &Path) {
+	std::fs::remove_file(download_file).ok();
+	std::fs::remove_file(meta_file(download_file)).ok();
+}
+
+fn is_json(content_type: &str) -> bool {
+	content_type
+		.split(';')
+		.next()
+		.is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
+}
+
+/// Writes the response body straight to the download file as it arrives. Bodies of error
+/// responses are discarded so they can't end up in the cache.
+#[derive(Debug)]
+pub struct ResponseHandler {
+	download_file: PathBuf,
+	progress: ImportProgress,
+	/// bytes already downloaded, if the download is resumed
+	resume_from: u64,
+	status: u32,
+	content_type: Option<String>,
+	content_length: Option<u64>,
+	validators: Validators,
+	writer: Option<BufWriter<File>>,
+	error: Option<std::io::Error>,
+}
+
+impl Handler for ResponseHandler {
+	fn header(&mut self, data: &[u8]) -> bool {
+		let line = String::from_utf8_lossy(data);
+		let line = line.trim();
+
+		if line.starts_with("HTTP/") {
+			// a new response begins, e.g. after a redirect
+			self.status = line
+				.split_whitespace()
+				.nth(1)
+				.and_then(|status| status.parse().ok())
+				.unwrap_or(0);
+			self.content_type = None;
+			self.content_length = None;
+			self.validators = Validators::default();
+			return true;
+		}
+
+		if let Some((name, value)) = line.split_once(':') {
+			let value = Some(value.trim().to_string());
+			match name.trim().to_ascii_lowercase().as_str() {
+				"content-type" => self.content_type = value,
+				"content-length" => self.content_length = value.and_then(|len| len.parse().ok()),
+				"etag" => self.validators.etag = value,
+				"last-modified" => self.validators.last_modified = value,
+				_ => {}
+			}
+		}
+
+		true
+	}
+
+	fn write(&mut self, data: &[u8]) -> Result<usize, WriteError> {
+		if self.progress.is_cancelled() {
+			return Ok(0);
+		}
+
+		if !self.accepts_body() {
+			return Ok(data.len());
+		}
+
+		if let Err(err) = self.write_body(data) {
+			self.error = Some(err);
+			// reporting less bytes than received makes curl abort the transfer
+			return Ok(0);
+		}
+
+		self.progress.downloaded(data.len() as u64);
+		Ok(data.len())
+	}
+}
+
+impl ResponseHandler {
+	fn new(download_file: PathBuf, progress: ImportProgress, resume_from: u64) -> Self {
+		Self {
+			download_file,
+			progress,
+			resume_from,
+			status: 0,
+			content_type: None,
+			content_length: None,
+			validators: Validators::default(),
+			writer: None,
+			error: None,
+		}
+	}
+
+	fn accepts_body(&self) -> bool {
+		matches!(self.status, 200 | 206) && self.content_type.as_deref().is_some_and(is_json)
+	}
+
+	fn write_body(&mut self, data: &[u8]) -> Result<(), std::io::Error> {
+		match &mut self.writer {
+			Some(writer) => writer.write_all(data),
+			None => {
+				let mut writer = self.open_writer()?;
+				writer.write_all(data)?;
+				self.writer = Some(writer);
+				Ok(())
+			}
+		}
+	}
+
+	fn open_writer(&self) -> Result<BufWriter<File>, std::io::Error> {
+		let file = if self.status == 206 {
+			self.progress.download_started(
+				self.resume_from,
+				self.content_length.map(|len| self.resume_from + len),
+			);
+			OpenOptions::new().append(true).open(&self.download_file)?
+		} else {
+			self.progress.download_started(0, self.content_length);
+			// the whole list is sent, start over and remember which version of it this is
+			let file = File::create(&self.download_file)?;
+			self.validators.save(&meta_file(&self.download_file))?;
+			file
+		};
+
+		Ok(BufWriter::new(file))
+	}
+
+	fn finish(&mut self) -> Result<(), std::io::Error> {
+		if let Some(err) = self.error.take() {
+			return Err(err);
+		}
+
+		if self.writer.is_none() && self.accepts_body() {
+			// empty body, nothing was written yet
+			self.writer = Some(self.open_writer()?);
+		}
+
+		match &mut self.writer {
+			Some(writer) => writer.flush(),
+			None => Ok(()),
+		}
+	}
+}
@@ -0,0 +1,267 @@
+use std::{
+	error::Error,
+	io::{Cursor, Read, Write},
+};
+
+use serde::{Deserialize, Serialize};
+use zip::{ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+use crate::mods::PackageVersion;
+
+/// Name of the profile file inside an r2modman export
+const PROFILE_FILE: &str = "export.r2x";
+
+/// Largest `export.r2x` read from an uploaded zip, so a small upload can't expand into a huge
+/// file in memory
+const MAX_PROFILE_FILE_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Every zip file starts with these bytes
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// r2modman / Thunderstore Mod Manager profile, stored as YAML in `export.r2x`
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+	pub profile_name: String,
+	pub mods: Vec<ProfileMod>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileMod {
+	/// full name of the mod, e.g. `BepInEx-BepInExPack`
+	pub name: String,
+	pub version: ProfileModVersion,
+	pub enabled: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileModVersion {
+	pub major: u32,
+	pub minor: u32,
+	pub patch: u32,
+}
+
+impl ProfileModVersion {
+	fn parse(version_number: &str) -> Option<Self> {
+		let mut parts = version_number.split('.').map(|part| part.parse().ok());
+
+		let version = Self {
+			major: parts.next()??,
+			minor: parts.next()??,
+			patch: parts.next()??,
+		};
+
+		if parts.next().is_some() {
+			return None;
+		}
+
+		Some(version)
+	}
+}
+
+impl Profile {
+	pub fn new(profile_name: &str, packages: &[PackageVersion]) -> Self {
+		let mods = packages
+			.iter()
+			.filter_map(|package| {
+				let version = ProfileModVersion::parse(&package.version_number);
+				if version.is_none() {
+					log::warn!(
+						"Can't add mod '{}' to profile: unsupported version number '{}'",
+						package.full_name,
+						package.version_number
+					);
+				}
+
+				version.map(|version| ProfileMod {
+					name: package.full_name.clone(),
+					version,
+					enabled: true,
+				})
+			})
+			.collect();
+
+		Self {
+			profile_name: profile_name.to_string(),
+			mods,
+		}
+	}
+
+	/// Read a profile from either a zipped `export.r2z` or a plain `export.r2x` file
+	pub fn parse(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+		if bytes.starts_with(ZIP_MAGIC) {
+			Self::from_r2z(bytes)
+		} else {
+			Self::from_r2x(bytes)
+		}
+	}
+
+	fn from_r2z(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+		let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+		let mut yaml = Vec::new();
+		archive
+			.by_name(PROFILE_FILE)?
+			.take(MAX_PROFILE_FILE_SIZE + 1)
+			.read_to_end(&mut yaml)?;
+
+		if yaml.len() as u64 > MAX_PROFILE_FILE_SIZE {
+			return Err(
+				format!("{PROFILE_FILE} is larger than {MAX_PROFILE_FILE_SIZE} bytes").into(),
+			);
+		}
+
+		Self::from_r2x(&yaml)
+	}
+
+	fn from_r2x(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+		let profile = serde_yaml::from_slice(bytes)?;
+		Ok(profile)
+	}
+
+	/// zip the profile into an `export.r2z` file which can be imported into r2modman
+	pub fn to_r2z(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+		let yaml = serde_yaml::to_string(self)?;
+
+		let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+		zip.start_file(PROFILE_FILE, SimpleFileOptions::default())?;
+		zip.write_all(yaml.as_bytes())?;
+
+		let bytes = zip.finish()?.into_inner();
+		Ok(bytes)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn package(full_name: &str, version_number: &str) -> PackageVersion {
+		PackageVersion {
+			full_name: full_name.to_string(),
+			version_number: version_number.to_string(),
+		}
+	}
+
+	#[test]
+	fn creating_profile_skips_unsupported_versions() {
+		let packages = vec![
+			package("BepInEx-BepInExPack", "5.4.2100"),
+			package("Cat-Broken", "1.0"),
+			package("Dog-Music", "1.2.3"),
+		];
+
+		let profile = Profile::new("liked", &packages);
+
+		let expected = Profile {
+			profile_name: "liked".to_string(),
+			mods: vec![
+				ProfileMod {
+					name: "BepInEx-BepInExPack".to_string(),
+					version: ProfileModVersion {
+						major: 5,
+						minor: 4,
+						patch: 2100,
+					},
+					enabled: true,
+				},
+				ProfileMod {
+					name: "Dog-Music".to_string(),
+					version: ProfileModVersion {
+						major: 1,
+						minor: 2,
+						patch: 3,
+					},
+					enabled: true,
+				},
+			],
+		};
+		assert_eq!(expected, profile);
+	}
+
+	#[test]
+	fn profile_export_contains_r2x_file() {
+		let profile = Profile::new("liked", &[package("Dog-Music", "1.2.3")]);
+		let bytes = profile.to_r2z().unwrap();
+
+		let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+		let mut yaml = String::new();
+		archive
+			.by_name("export.r2x")
+			.unwrap()
+			.read_to_string(&mut yaml)
+			.unwrap();
+
+		let expected = "profileName: liked
+mods:
+- name: Dog-Music
+  version:
+    major: 1
+    minor: 2
+    patch: 3
+  enabled: true
+";
+		assert_eq!(expected, yaml);
+	}
+
+	#[test]
+	fn parsing_exported_profile() {
+		let profile = Profile::new(
+			"liked",
+			&[
+				package("BepInEx-BepInExPack", "5.4.2100"),
+				package("Dog-Music", "1.2.3"),
+			],
+		);
+		let bytes = profile.to_r2z().unwrap();
+
+		let result = Profile::parse(&bytes).unwrap();
+		assert_eq!(profile, result);
+	}
+
+	#[test]
+	fn parsing_r2x_file() {
+		let yaml = "profileName: Default
+mods:
+  - name: BepInEx-BepInExPack
+    version:
+      major: 5
+      minor: 4
+      patch: 2100
+    enabled: true
+  - name: Cat-Suits
+    version:
+      major: 1
+      minor: 0
+      patch: 0
+    enabled: false
+";
+
+		let result = Profile::parse(yaml.as_bytes()).unwrap();
+
+		let names = result
+			.mods
+			.iter()
+			.map(|m| m.name.as_str())
+			.collect::<Vec<_>>();
+		assert_eq!("Default", result.profile_name);
+		assert_eq!(vec!["BepInEx-BepInExPack", "Cat-Suits"], names);
+	}
+
+	#[test]
+	fn parsing_invalid_profile() {
+		assert!(Profile::parse(b"not a profile").is_err());
+		assert!(Profile::parse(b"PK\x03\x04 definitely not a zip").is_err());
+	}
+
+	#[test]
+	fn parsing_too_large_profile() {
+		let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+		zip.start_file(PROFILE_FILE, SimpleFileOptions::default())
+			.unwrap();
+		let padding = vec![b' '; MAX_PROFILE_FILE_SIZE as usize + 1];
+		zip.write_all(&padding).unwrap();
+		let bytes = zip.finish().unwrap().into_inner();
+
+		assert!(Profile::parse(&bytes).is_err());
+	}
+}
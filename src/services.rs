@@ -10,12 +10,17 @@ use actix_web::{
 	web::{Data, Html, ReqData},
 };
 use tera::{Context, Tera};
+use time::{OffsetDateTime, macros::format_description};
 use users::TokenClaims;
 
 use crate::{db::Database, middlewares::TokenValidator};
 
+pub mod history;
+pub mod icons;
 pub mod import_mods;
+pub mod mod_details;
 pub mod ratings;
+pub mod search;
 pub mod settings;
 pub mod users;
 
@@ -23,6 +28,37 @@ pub fn header_redirect_to(to_url: &str) -> impl TryIntoHeaderPair {
 	(header::REFRESH, format!("0; url={to_url}"))
 }
 
+pub fn format_size(bytes: u64) -> String {
+	format!("{:.1} MB", bytes as f64 / 1_000_000.0)
+}
+
+pub fn format_day(date: OffsetDateTime) -> String {
+	date.format(format_description!("[year]-[month]-[day]"))
+		.unwrap_or_else(|err| {
+			log::error!("Error formatting date: {err}");
+			"---".to_string()
+		})
+}
+
+/// `count` with thousands separated, e.g. `1,234,567`
+pub fn format_count(count: i64) -> String {
+	let digits = count.unsigned_abs().to_string();
+	let mut formatted = String::new();
+
+	for (i, digit) in digits.chars().enumerate() {
+		if i > 0 && (digits.len() - i).is_multiple_of(3) {
+			formatted.push(',');
+		}
+		formatted.push(digit);
+	}
+
+	if count < 0 {
+		formatted.insert(0, '-');
+	}
+
+	formatted
+}
+
 #[get("/", wrap = "TokenValidator")]
 async fn home_page(
 	template: Data<Mutex<Tera>>,
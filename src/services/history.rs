@@ -0,0 +1,186 @@
+use std::sync::Mutex;
+
+use actix_web::{
+	HttpRequest, get,
+	web::{Data, Html, ReqData},
+};
+use serde::{Deserialize, Serialize};
+use serde_qs::actix::QsQuery;
+use tera::{Context, Tera};
+
+use crate::{
+	db::{Database, HistorySort, RatingHistoryOptions},
+	env::Env,
+	middlewares::TokenValidator,
+	mods::{RatedMod, Rating},
+	services::{format_day, settings::load_settings},
+};
+
+use super::users::TokenClaims;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+const PAGE_SIZES: [i64; 4] = [20, 50, 100, 200];
+
+/// Filters, order and page of the history. The filters are empty strings when not used, as
+/// that's what the form sends for them.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct HistoryParams {
+	#[serde(default)]
+	rating: String,
+	#[serde(default)]
+	category: String,
+	#[serde(default)]
+	owner: String,
+	#[serde(default)]
+	sort: HistorySort,
+	#[serde(default = "first_page")]
+	page: i64,
+	#[serde(default = "default_page_size")]
+	size: i64,
+}
+
+fn first_page() -> i64 {
+	1
+}
+
+fn default_page_size() -> i64 {
+	DEFAULT_PAGE_SIZE
+}
+
+impl HistoryParams {
+	fn rating(&self) -> Option<Rating> {
+		match self.rating.as_str() {
+			"Like" => Some(Rating::Like),
+			"Dislike" => Some(Rating::Dislike),
+			_ => None,
+		}
+	}
+
+	fn url(&self, page: i64) -> String {
+		let params = HistoryParams {
+			page,
+			..self.clone()
+		};
+
+		match serde_qs::to_string(&params) {
+			Ok(query) => format!("/history?{query}"),
+			Err(err) => {
+				log::error!("Error serializing history parameters: {err}");
+				"/history".to_string()
+			}
+		}
+	}
+}
+
+/// non-empty trimmed `value`
+fn filter_value(value: &str) -> Option<String> {
+	Some(value.trim())
+		.filter(|value| !value.is_empty())
+		.map(str::to_string)
+}
+
+#[derive(Debug, Serialize)]
+struct HistoryRow {
+	id: String,
+	name: String,
+	owner: String,
+	categories: String,
+	delisted: bool,
+	rating: Rating,
+	rated_at: String,
+	updated_date: String,
+}
+
+impl From<RatedMod> for HistoryRow {
+	fn from(rated: RatedMod) -> Self {
+		let modd = rated.modd;
+		Self {
+			id: modd.id.to_string(),
+			categories: modd.categories.join(", "),
+			delisted: modd.delisted,
+			rating: rated.rating,
+			rated_at: rated
+				.rated_at
+				.map(format_day)
+				.unwrap_or_else(|| "-".to_string()),
+			updated_date: format_day(modd.updated_date),
+			name: modd.name,
+			owner: modd.owner,
+		}
+	}
+}
+
+/// All ratings of the user, both likes and dislikes, filtered and sorted
+#[get("/history", wrap = "TokenValidator")]
+async fn rating_history_page(
+	template: Data<Mutex<Tera>>,
+	db: Data<Database>,
+	env: Data<Env>,
+	req_user: ReqData<TokenClaims>,
+	request: HttpRequest,
+	params: QsQuery<HistoryParams>,
+) -> Result<Html, actix_web::Error> {
+	let mut params = params.into_inner();
+	params.page = params.page.max(1);
+	params.size = params.size.clamp(1, MAX_PAGE_SIZE);
+
+	let settings = load_settings(&request);
+	let community = settings.selected_community(&env);
+
+	let categories = db
+		.get_categories(community)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?
+		.into_iter()
+		.map(|category| category.name)
+		.collect::<Vec<_>>();
+
+	let options = RatingHistoryOptions {
+		community: community.to_string(),
+		rating: params.rating(),
+		category: filter_value(&params.category),
+		owner: filter_value(&params.owner),
+		sort: params.sort,
+		limit: params.size,
+		offset: (params.page - 1).saturating_mul(params.size),
+	};
+
+	let history = db
+		.get_rating_history(&options, req_user.id)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+
+	let pages = (history.total + params.size - 1) / params.size;
+	let rows = history
+		.items
+		.into_iter()
+		.map(HistoryRow::from)
+		.collect::<Vec<_>>();
+
+	let mut ctx = Context::new();
+	ctx.insert("params", &params);
+	ctx.insert("categories", &categories);
+	ctx.insert("page_sizes", &PAGE_SIZES);
+	ctx.insert("rows", &rows);
+	ctx.insert("total", &history.total);
+	ctx.insert("pages", &pages);
+	ctx.insert("current_url", &params.url(params.page));
+	if params.page > 1 {
+		ctx.insert("previous_url", &params.url(params.page - 1));
+	}
+	if params.page < pages {
+		ctx.insert("next_url", &params.url(params.page + 1));
+	}
+
+	let html = template
+		.lock()
+		.unwrap()
+		.render("history.html", &ctx)
+		.map_err(|err| {
+			log::error!("{err}");
+			actix_web::error::ErrorInternalServerError("Template error")
+		})?;
+
+	Ok(Html::new(html))
+}
@@ -0,0 +1,62 @@
+use actix_files::NamedFile;
+use actix_web::{
+	Responder, get,
+	http::header::{CACHE_CONTROL, CacheControl, CacheDirective},
+	web::{Data, Path},
+};
+use uuid::Uuid;
+
+use crate::{
+	db::Database,
+	env::Env,
+	middlewares::TokenValidator,
+	mods::{IconStore, Mod},
+};
+
+/// icons are linked with their file name in the url, so a changed icon gets a new url
+const ICON_MAX_AGE: u32 = 365 * 24 * 60 * 60;
+/// the icon might be fetched on the next import
+const PLACEHOLDER_MAX_AGE: u32 = 60 * 60;
+
+/// Icon of the mod from the icon cache, or a placeholder if it isn't cached
+#[get("/icons/{mod_id}", wrap = "TokenValidator")]
+pub async fn mod_icon(
+	db: Data<Database>,
+	env: Data<Env>,
+	mod_id: Path<Uuid>,
+) -> Result<impl Responder, actix_web::Error> {
+	let icon_file = db
+		.get_icon_file(&mod_id)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+
+	let icon = icon_file
+		.and_then(|icon_file| IconStore::new(&env).path(&icon_file))
+		.and_then(|path| NamedFile::open(path).ok());
+
+	let (file, max_age) = match icon {
+		Some(icon) => (icon, ICON_MAX_AGE),
+		None => (
+			NamedFile::open("static/icon_placeholder.svg")?,
+			PLACEHOLDER_MAX_AGE,
+		),
+	};
+
+	Ok(file.customize().insert_header((
+		CACHE_CONTROL,
+		CacheControl(vec![
+			CacheDirective::Private,
+			CacheDirective::MaxAge(max_age),
+		]),
+	)))
+}
+
+/// Icon of the mod served from the icon cache. The url changes with the icon, so browsers can
+/// cache it for long. Icons are never linked from Thunderstore, a mod whose icon isn't cached
+/// shows the placeholder until the icon is fetched after an import.
+pub fn icon_url(modd: &Mod) -> String {
+	match &modd.icon_file {
+		Some(icon_file) => format!("/icons/{}?v={icon_file}", modd.id),
+		None => format!("/icons/{}", modd.id),
+	}
+}
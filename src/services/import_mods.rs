@@ -1,42 +1,103 @@
 use std::sync::Mutex;
 
-use actix_files::NamedFile;
+use actix_multipart::form::{MultipartForm, tempfile::TempFile, text::Text};
 use actix_web::{
-	Either, Responder, get, post,
-	web::{Data, Html},
+	Either, HttpResponse, Responder, get, post,
+	web::{Data, Form, Html, Json, Path},
 };
+use serde::{Deserialize, Serialize};
 use tera::{Context, Tera};
 use time::{OffsetDateTime, macros::format_description};
 
 use crate::{
 	db::Database,
+	env::Env,
 	middlewares::{PrivilegeValidator, TokenValidator},
+	mods::{
+		ImportPhase, ImportProgress, ImportRun, ImportSchedule, ImportTrigger, ProgressReport,
+		Snapshot, ThunderstoreSource, UploadSource, next_import_time,
+	},
+	services::{format_size, header_redirect_to},
 };
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportRequest {
+	/// refresh the mods as configured in `MOD_REFRESH`
+	Refresh(ImportTrigger),
+	/// roll the mods of a community back to a snapshot of its package list
+	Rollback { community: String, snapshot: String },
+	/// import the package list of a community uploaded by an admin
+	Upload { community: String },
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct ImportStatus {
-	pub import_requested: bool,
+	/// the next import to run, None if no import has been requested
+	pub import_requested: Option<ImportRequest>,
 	pub import_in_progress: bool,
+	/// progress of the running import
+	pub progress: ImportProgress,
 }
 
+impl ImportStatus {
+	fn is_busy(&self) -> bool {
+		self.import_requested.is_some() || self.import_in_progress
+	}
+
+	/// Take the requested import to run it, None if there's no request or an import is already
+	/// running. Imports requested while it runs are kept for after it.
+	pub fn start_requested(&mut self) -> Option<(ImportRequest, ImportProgress)> {
+		if self.import_in_progress {
+			return None;
+		}
+
+		let request = self.import_requested.take()?;
+		self.import_in_progress = true;
+		self.progress.start();
+		Some((request, self.progress.clone()))
+	}
+
+	pub fn finish(&mut self) {
+		self.import_in_progress = false;
+		self.progress.finish();
+	}
+}
+
+/// largest package list that can be uploaded, the package lists of big communities are
+/// hundreds of megabytes uncompressed
+pub const PACKAGE_LIST_UPLOAD_LIMIT: usize = 1024 * 1024 * 1024;
+
 #[get("/import-mods", wrap = "PrivilegeValidator", wrap = "TokenValidator")]
 pub async fn import_mods_page(
 	template: Data<Mutex<Tera>>,
 	db: Data<Database>,
+	env: Data<Env>,
 	import_status: Data<Mutex<ImportStatus>>,
 ) -> Result<impl Responder, actix_web::Error> {
-	let import_in_progress = {
+	let (import_in_progress, progress) = {
 		let import_status = import_status.lock().unwrap();
-		import_status.import_requested || import_status.import_in_progress
+		(import_status.is_busy(), import_status.progress.report())
 	};
 
+	let mut ctx = Context::new();
+
 	if import_in_progress {
-		return Ok(Either::Left(NamedFile::open(
-			"static/import_in_progress.html",
-		)));
-	}
+		let progress = progress.map(ProgressView::new);
+		let cancelling = progress.as_ref().is_some_and(|p| p.cancel_requested);
+		ctx.insert("progress", &progress);
+		ctx.insert("cancelling", &cancelling);
 
-	let mut ctx = Context::new();
+		let html = template
+			.lock()
+			.unwrap()
+			.render("import_in_progress.html", &ctx)
+			.map_err(|err| {
+				log::error!("{err}");
+				actix_web::error::ErrorInternalServerError("Template error")
+			})?;
+
+		return Ok(Html::new(html));
+	}
 
 	let latest_import = db
 		.latest_mod_import_date()
@@ -45,6 +106,23 @@ pub async fn import_mods_page(
 
 	ctx.insert("latest_import", &latest_import_string(latest_import));
 
+	if let Some(schedule) = env.mod_refresh_options.schedule() {
+		let next_import = next_import_time(&db, &env)
+			.await
+			.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+
+		ctx.insert("next_import", &next_import_string(schedule, next_import));
+	}
+
+	let latest_summary = db
+		.latest_import_summary()
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+
+	if let Some(summary) = latest_summary {
+		ctx.insert("summary", &summary);
+	}
+
 	let html = template
 		.lock()
 		.unwrap()
@@ -54,34 +132,523 @@ pub async fn import_mods_page(
 			actix_web::error::ErrorInternalServerError("Template error")
 		})?;
 
-	Ok(Either::Right(Html::new(html)))
+	Ok(Html::new(html))
 }
 
 #[post("/import-mods", wrap = "PrivilegeValidator", wrap = "TokenValidator")]
 pub async fn import_mods(import_status: Data<Mutex<ImportStatus>>) -> impl Responder {
-	log::info!("Mod reimport requested");
-	import_status.lock().unwrap().import_requested = true;
-	NamedFile::open("static/import_in_progress.html")
+	let mut import_status = import_status.lock().unwrap();
+
+	// the import page shows the progress of the import that is already requested or running
+	if import_status.is_busy() {
+		log::info!("Mod reimport requested while another import is in progress, ignoring it");
+	} else {
+		log::info!("Mod reimport requested");
+		import_status.import_requested = Some(ImportRequest::Refresh(ImportTrigger::Manual));
+	}
+
+	HttpResponse::Ok()
+		.insert_header(header_redirect_to("/import-mods"))
+		.finish()
 }
 
-fn latest_import_string(latest_import: Option<OffsetDateTime>) -> String {
-	let date = match latest_import {
-		None => return "Never".to_string(),
-		Some(date) => date,
+#[derive(Debug, Serialize)]
+struct ImportProgressResponse {
+	/// an import is requested or running, the import page shows its progress
+	busy: bool,
+	/// None until the requested import starts
+	progress: Option<ProgressView>,
+}
+
+/// Progress of the import for the import in progress page, which polls it instead of reloading
+#[get(
+	"/import-mods/progress",
+	wrap = "PrivilegeValidator",
+	wrap = "TokenValidator"
+)]
+pub async fn import_progress(import_status: Data<Mutex<ImportStatus>>) -> impl Responder {
+	let import_status = import_status.lock().unwrap();
+
+	Json(ImportProgressResponse {
+		busy: import_status.is_busy(),
+		progress: import_status.progress.report().map(ProgressView::new),
+	})
+}
+
+/// Cancel the running import, or the requested one if it hasn't started yet. A running import
+/// is rolled back, so the mods stay as they were before it.
+#[post(
+	"/import-mods/cancel",
+	wrap = "PrivilegeValidator",
+	wrap = "TokenValidator"
+)]
+pub async fn cancel_import(import_status: Data<Mutex<ImportStatus>>) -> impl Responder {
+	let mut import_status = import_status.lock().unwrap();
+
+	if import_status.import_in_progress {
+		log::info!("Cancelling the running mod import");
+		import_status.progress.cancel();
+	} else if import_status.import_requested.take().is_some() {
+		log::info!("Cancelled the requested mod import before it started");
+	}
+
+	HttpResponse::Ok()
+		.insert_header(header_redirect_to("/import-mods"))
+		.finish()
+}
+
+#[derive(Debug, Serialize)]
+struct ProgressView {
+	phase: String,
+	community: Option<String>,
+	/// e.g. `2/5`, None if the phase isn't done in chunks
+	chunk: Option<String>,
+	mods_processed: usize,
+	downloaded: Option<String>,
+	elapsed: String,
+	cancel_requested: bool,
+}
+
+impl ProgressView {
+	fn new(progress: ProgressReport) -> Self {
+		let phase = match progress.phase {
+			ImportPhase::Starting => "Starting",
+			ImportPhase::Downloading => "Downloading package list",
+			ImportPhase::Parsing => "Parsing package list",
+			ImportPhase::InsertingCategories => "Inserting categories",
+			ImportPhase::InsertingMods => "Inserting mods",
+			ImportPhase::InsertingVersions => "Inserting mod versions",
+			ImportPhase::InsertingDependencies => "Inserting mod dependencies",
+			ImportPhase::InsertingCategoryJunctions => "Inserting mod categories",
+			ImportPhase::Delisting => "Marking removed mods as delisted",
+			ImportPhase::Committing => "Saving the import",
+			ImportPhase::FetchingIcons => "Downloading mod icons",
+		};
+
+		let chunk =
+			(progress.chunks > 0).then(|| format!("{}/{}", progress.chunk, progress.chunks));
+
+		let downloaded = (progress.bytes_downloaded > 0).then(|| match progress.bytes_total {
+			Some(total) => format!(
+				"{} of {}",
+				format_size(progress.bytes_downloaded),
+				format_size(total)
+			),
+			None => format_size(progress.bytes_downloaded),
+		});
+
+		Self {
+			phase: phase.to_string(),
+			community: progress.community,
+			chunk,
+			mods_processed: progress.mods_processed,
+			downloaded,
+			elapsed: format_duration(progress.elapsed_secs as i64),
+			cancel_requested: progress.cancel_requested,
+		}
+	}
+}
+
+/// how many problems are shown on the problems page of an import run
+const PROBLEMS_SHOWN: i32 = 1000;
+
+#[derive(Debug, Serialize)]
+struct ImportRunRow {
+	id: i32,
+	problems: i64,
+	started_at: String,
+	duration: String,
+	trigger: String,
+	source: String,
+	outcome: String,
+	error: Option<String>,
+	summary: Option<String>,
+}
+
+impl ImportRunRow {
+	fn new(run: ImportRun) -> Self {
+		let duration = match run.finished_at {
+			Some(finished_at) => format_duration((finished_at - run.started_at).whole_seconds()),
+			None => "---".to_string(),
+		};
+
+		let summary = run.added.map(|added| {
+			format!(
+				"{} added, {} updated, {} unchanged, {} failed, {} delisted",
+				added,
+				run.updated.unwrap_or_default(),
+				run.unchanged.unwrap_or_default(),
+				run.failed.unwrap_or_default(),
+				run.delisted.unwrap_or_default()
+			)
+		});
+
+		Self {
+			id: run.id,
+			problems: run.problems,
+			started_at: format_date(run.started_at),
+			duration,
+			trigger: format!("{:?}", run.trigger),
+			source: format!("{:?}", run.source),
+			outcome: format!("{:?}", run.outcome),
+			error: run.error,
+			summary,
+		}
+	}
+}
+
+#[get(
+	"/import-history",
+	wrap = "PrivilegeValidator",
+	wrap = "TokenValidator"
+)]
+pub async fn import_history_page(
+	template: Data<Mutex<Tera>>,
+	db: Data<Database>,
+) -> Result<impl Responder, actix_web::Error> {
+	let runs = db
+		.get_import_runs(100)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?
+		.into_iter()
+		.map(ImportRunRow::new)
+		.collect::<Vec<_>>();
+
+	let mut ctx = Context::new();
+	ctx.insert("runs", &runs);
+
+	let html = template
+		.lock()
+		.unwrap()
+		.render("import_history.html", &ctx)
+		.map_err(|err| {
+			log::error!("{err}");
+			actix_web::error::ErrorInternalServerError("Template error")
+		})?;
+
+	Ok(Html::new(html))
+}
+
+#[derive(Debug, Serialize)]
+struct ProblemCount {
+	kind: String,
+	count: i64,
+}
+
+#[get(
+	"/import-history/{run_id}",
+	wrap = "PrivilegeValidator",
+	wrap = "TokenValidator"
+)]
+pub async fn import_problems_page(
+	template: Data<Mutex<Tera>>,
+	db: Data<Database>,
+	run_id: Path<i32>,
+) -> Result<impl Responder, actix_web::Error> {
+	let run_id = run_id.into_inner();
+
+	let counts = db
+		.count_import_problems(run_id)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?
+		.into_iter()
+		.map(|(kind, count)| ProblemCount {
+			kind: format!("{kind:?}"),
+			count,
+		})
+		.collect::<Vec<_>>();
+
+	let problems = db
+		.get_import_problems(run_id, PROBLEMS_SHOWN)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+
+	let total = counts.iter().map(|count| count.count).sum::<i64>();
+
+	let mut ctx = Context::new();
+	ctx.insert("run_id", &run_id);
+	ctx.insert("counts", &counts);
+	ctx.insert("problems", &problems);
+	ctx.insert("total", &total);
+
+	let html = template
+		.lock()
+		.unwrap()
+		.render("import_problems.html", &ctx)
+		.map_err(|err| {
+			log::error!("{err}");
+			actix_web::error::ErrorInternalServerError("Template error")
+		})?;
+
+	Ok(Html::new(html))
+}
+
+#[derive(Debug, Serialize)]
+struct SnapshotRow {
+	community: String,
+	name: String,
+	taken_at: String,
+	size: String,
+}
+
+impl SnapshotRow {
+	fn new(community: &str, snapshot: Snapshot) -> Self {
+		Self {
+			community: community.to_string(),
+			name: snapshot.name,
+			taken_at: format_date(snapshot.taken_at),
+			size: format_size(snapshot.size),
+		}
+	}
+}
+
+#[get(
+	"/import-snapshots",
+	wrap = "PrivilegeValidator",
+	wrap = "TokenValidator"
+)]
+pub async fn import_snapshots_page(
+	template: Data<Mutex<Tera>>,
+	env: Data<Env>,
+) -> Result<Html, actix_web::Error> {
+	render_import_snapshots_page(&template, &env, &Context::new())
+}
+
+fn render_import_snapshots_page(
+	template: &Mutex<Tera>,
+	env: &Env,
+	ctx: &Context,
+) -> Result<Html, actix_web::Error> {
+	let mut snapshots = Vec::new();
+	for community in &env.communities {
+		let community_snapshots = ThunderstoreSource::new(env, community)
+			.snapshots()
+			.map_err(|err| {
+				log::error!("Error listing snapshots of '{community}': {err}");
+				actix_web::error::ErrorInternalServerError("Can't read snapshots")
+			})?;
+
+		snapshots.extend(
+			community_snapshots
+				.into_iter()
+				.map(|snapshot| SnapshotRow::new(community, snapshot)),
+		);
+	}
+
+	let mut ctx = ctx.clone();
+	ctx.insert("snapshots", &snapshots);
+
+	let html = template
+		.lock()
+		.unwrap()
+		.render("import_snapshots.html", &ctx)
+		.map_err(|err| {
+			log::error!("{err}");
+			actix_web::error::ErrorInternalServerError("Template error")
+		})?;
+
+	Ok(Html::new(html))
+}
+
+#[derive(Deserialize)]
+struct RollbackForm {
+	community: String,
+	snapshot: String,
+}
+
+#[post(
+	"/import-snapshots/rollback",
+	wrap = "PrivilegeValidator",
+	wrap = "TokenValidator"
+)]
+pub async fn rollback_mods(
+	form: Form<RollbackForm>,
+	template: Data<Mutex<Tera>>,
+	env: Data<Env>,
+	import_status: Data<Mutex<ImportStatus>>,
+) -> Result<Either<Html, HttpResponse>, actix_web::Error> {
+	let RollbackForm {
+		community,
+		snapshot,
+	} = form.into_inner();
+
+	let snapshot_exists = env.communities.contains(&community)
+		&& ThunderstoreSource::new(&env, &community)
+			.snapshots()
+			.map_err(|err| {
+				log::error!("Error listing snapshots of '{community}': {err}");
+				actix_web::error::ErrorInternalServerError("Can't read snapshots")
+			})?
+			.iter()
+			.any(|s| s.name == snapshot);
+
+	if !snapshot_exists {
+		return Err(actix_web::error::ErrorBadRequest("Unknown snapshot"));
+	}
+
+	let mut import_status = import_status.lock().unwrap();
+	// the rollback would replace the requested import, e.g. an uploaded package list
+	if import_status.is_busy() {
+		drop(import_status);
+		let mut ctx = Context::new();
+		ctx.insert(
+			"error",
+			"Another import is running, try again once it has finished.",
+		);
+		return render_import_snapshots_page(&template, &env, &ctx).map(Either::Left);
+	}
+
+	log::info!("Rollback of '{community}' to snapshot '{snapshot}' requested");
+	import_status.import_requested = Some(ImportRequest::Rollback {
+		community,
+		snapshot,
+	});
+
+	Ok(Either::Right(
+		HttpResponse::Ok()
+			.insert_header(header_redirect_to("/import-mods"))
+			.finish(),
+	))
+}
+
+#[get("/import-upload", wrap = "PrivilegeValidator", wrap = "TokenValidator")]
+pub async fn import_upload_page(
+	template: Data<Mutex<Tera>>,
+	env: Data<Env>,
+) -> Result<Html, actix_web::Error> {
+	render_import_upload_page(&template, &env, &Context::new())
+}
+
+#[derive(MultipartForm)]
+pub struct PackageListUpload {
+	community: Text<String>,
+	package_list: TempFile,
+}
+
+/// Import a package list uploaded by an admin, plain or gzip compressed. It's checked to parse
+/// before the import is requested. Handles `POST /import-upload`, which is registered in `main`
+/// so that only it accepts uploads as large as `PACKAGE_LIST_UPLOAD_LIMIT`.
+pub async fn upload_package_list(
+	template: Data<Mutex<Tera>>,
+	env: Data<Env>,
+	import_status: Data<Mutex<ImportStatus>>,
+	MultipartForm(upload): MultipartForm<PackageListUpload>,
+) -> Result<Either<Html, HttpResponse>, actix_web::Error> {
+	let community = upload.community.into_inner();
+	if !env.communities.contains(&community) {
+		return Err(actix_web::error::ErrorBadRequest("Unknown community"));
+	}
+
+	let render_error = |error: &str| {
+		let mut ctx = Context::new();
+		ctx.insert("error", error);
+		render_import_upload_page(&template, &env, &ctx).map(Either::Left)
 	};
 
-	let date_str = date.format(format_description!(
-		"[year]-[month]-[day] [hour]:[minute]UTC"
-	));
+	// the upload would replace the package list of a running import
+	if import_status.lock().unwrap().is_busy() {
+		return render_error("Another import is running, try again once it has finished.");
+	}
+
+	// checked where it was uploaded, the temporary file is removed if the upload isn't imported
+	let uploaded = UploadSource::from_file(&community, upload.package_list.file.path());
+	let mods_count = actix_rt::task::spawn_blocking(move || uploaded.validate())
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Can't read the upload"))?;
 
-	let date_str = match date_str {
-		Ok(str) => str,
+	let error = match mods_count {
+		Ok(0) => Some("The package list doesn't contain any mods.".to_string()),
+		Ok(_) => None,
 		Err(err) => {
-			log::error!("Error formatting date: {err}");
-			return "---".to_string();
+			log::warn!("Uploaded package list is not valid: {err}");
+			Some(format!(
+				"Can't read the package list, please upload the JSON returned by the Thunderstore package API, or a gzip of it: {err}"
+			))
 		}
 	};
 
+	if let Some(error) = error {
+		return render_error(&error);
+	}
+
+	let mut import_status = import_status.lock().unwrap();
+	if import_status.is_busy() {
+		// another import was requested while the upload was being checked
+		drop(import_status);
+		return render_error("Another import is running, try again once it has finished.");
+	}
+
+	let mod_source = UploadSource::new(&env, &community);
+	save_upload(upload.package_list, mod_source.file()).map_err(|err| {
+		log::error!("Failed to save uploaded package list: {err}");
+		actix_web::error::ErrorInternalServerError("Can't save the upload")
+	})?;
+
+	log::info!("Import of uploaded package list of '{community}' requested");
+	import_status.import_requested = Some(ImportRequest::Upload { community });
+
+	Ok(Either::Right(
+		HttpResponse::Ok()
+			.insert_header(header_redirect_to("/import-mods"))
+			.finish(),
+	))
+}
+
+/// Move the uploaded file to `path`, copying it if it's on another file system
+fn save_upload(upload: TempFile, path: &std::path::Path) -> Result<(), std::io::Error> {
+	if let Some(dir) = path.parent() {
+		std::fs::create_dir_all(dir)?;
+	}
+
+	if let Err(err) = upload.file.persist(path) {
+		std::fs::copy(err.file.path(), path)?;
+	}
+
+	Ok(())
+}
+
+fn render_import_upload_page(
+	template: &Mutex<Tera>,
+	env: &Env,
+	ctx: &Context,
+) -> Result<Html, actix_web::Error> {
+	let mut ctx = ctx.clone();
+	ctx.insert("communities", &env.communities);
+
+	let html = template
+		.lock()
+		.unwrap()
+		.render("import_upload.html", &ctx)
+		.map_err(|err| {
+			log::error!("{err}");
+			actix_web::error::ErrorInternalServerError("Template error")
+		})?;
+
+	Ok(Html::new(html))
+}
+
+fn format_duration(seconds: i64) -> String {
+	format!("{}m {}s", seconds / 60, seconds % 60)
+}
+
+fn format_date(date: OffsetDateTime) -> String {
+	date.format(format_description!(
+		"[year]-[month]-[day] [hour]:[minute]UTC"
+	))
+	.unwrap_or_else(|err| {
+		log::error!("Error formatting date: {err}");
+		"---".to_string()
+	})
+}
+
+fn latest_import_string(latest_import: Option<OffsetDateTime>) -> String {
+	let date = match latest_import {
+		None => return "Never".to_string(),
+		Some(date) => date,
+	};
+
+	let date_str = format_date(date);
+
 	let now = OffsetDateTime::now_utc();
 	let elapsed = now - date;
 
@@ -101,3 +668,55 @@ fn latest_import_string(latest_import: Option<OffsetDateTime>) -> String {
 
 	format!("{date_str} ({time_since})")
 }
+
+fn next_import_string(schedule: &ImportSchedule, next_import: Option<OffsetDateTime>) -> String {
+	let Some(next_import) = next_import else {
+		return "Never".to_string();
+	};
+
+	let date_str = match schedule {
+		ImportSchedule::Interval(_) => format_date(next_import),
+		ImportSchedule::Cron(schedule) => {
+			format!("{} (schedule: {schedule})", schedule.format(next_import))
+		}
+	};
+
+	if next_import <= OffsetDateTime::now_utc() {
+		format!("{date_str}, due now")
+	} else {
+		date_str
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn import_requested_during_run_is_kept() {
+		let mut status = ImportStatus {
+			import_requested: Some(ImportRequest::Refresh(ImportTrigger::Manual)),
+			..Default::default()
+		};
+
+		let (request, _) = status.start_requested().unwrap();
+		assert_eq!(ImportRequest::Refresh(ImportTrigger::Manual), request);
+		assert!(status.is_busy());
+
+		// requested while the import runs, it waits for the running one
+		let upload = ImportRequest::Upload {
+			community: "lethal-company".to_string(),
+		};
+		status.import_requested = Some(upload.clone());
+		assert!(status.start_requested().is_none());
+
+		status.finish();
+		assert!(status.is_busy());
+		let (request, _) = status.start_requested().unwrap();
+		assert_eq!(upload, request);
+
+		status.finish();
+		assert!(!status.is_busy());
+		assert!(status.start_requested().is_none());
+	}
+}
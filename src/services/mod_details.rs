@@ -0,0 +1,117 @@
+use std::sync::Mutex;
+
+use actix_web::{
+	HttpRequest, get,
+	web::{Data, Html, Path, ReqData},
+};
+use serde::Serialize;
+use tera::{Context, Tera};
+use uuid::Uuid;
+
+use crate::{
+	db::Database,
+	env::Env,
+	middlewares::TokenValidator,
+	mods::Version,
+	services::{format_count, format_day, format_size, icons::icon_url, settings::load_settings},
+};
+
+use super::users::TokenClaims;
+
+#[derive(Debug, Serialize)]
+struct VersionRow {
+	version_number: String,
+	date_created: String,
+	downloads: String,
+	file_size: String,
+	is_active: bool,
+}
+
+impl From<Version> for VersionRow {
+	fn from(version: Version) -> Self {
+		Self {
+			version_number: version.version_number,
+			date_created: format_day(version.date_created),
+			downloads: format_count(version.downloads),
+			file_size: format_size(version.file_size.max(0) as u64),
+			is_active: version.is_active,
+		}
+	}
+}
+
+/// Everything stored about a mod of the selected community, how the users of the instance have
+/// rated it and the viewer's own rating
+#[get("/mod/{mod_id}", wrap = "TokenValidator")]
+async fn mod_details_page(
+	template: Data<Mutex<Tera>>,
+	db: Data<Database>,
+	env: Data<Env>,
+	req_user: ReqData<TokenClaims>,
+	request: HttpRequest,
+	mod_id: Path<Uuid>,
+) -> Result<Html, actix_web::Error> {
+	let settings = load_settings(&request);
+	let community = settings.selected_community(&env);
+
+	let modd = db
+		.get_mod(&mod_id, community)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?
+		.ok_or_else(|| actix_web::error::ErrorNotFound("Mod not found"))?;
+
+	let versions = db
+		.get_mod_versions(&modd.id)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?
+		.into_iter()
+		.map(VersionRow::from)
+		.collect::<Vec<_>>();
+
+	let dependencies = db
+		.get_dependencies(&modd.id, community)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+
+	let counts = db
+		.get_rating_counts(&modd.id)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+
+	let rating = db
+		.get_mod_rating(&modd.id, req_user.id)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+
+	let mut ctx = Context::new();
+	ctx.insert("name", &modd.name);
+	ctx.insert("owner", &modd.owner);
+	ctx.insert("icon_url", &icon_url(&modd));
+	ctx.insert("description", &modd.description);
+	ctx.insert("package_url", &modd.package_url);
+	ctx.insert("mod_id", &modd.id.to_string());
+	ctx.insert("delisted", &modd.delisted);
+	ctx.insert("categories", &modd.categories.join(", "));
+	ctx.insert("dependencies", &dependencies);
+	ctx.insert("downloads", &format_count(modd.downloads));
+	ctx.insert("date_created", &format_day(modd.date_created));
+	ctx.insert("updated_date", &format_day(modd.updated_date));
+	ctx.insert("pinned", &modd.pinned);
+	ctx.insert("website_url", &modd.website_url);
+	ctx.insert("donation_link", &modd.donation_link);
+	ctx.insert("versions", &versions);
+	ctx.insert("likes", &counts.likes);
+	ctx.insert("dislikes", &counts.dislikes);
+	ctx.insert("rating", &rating);
+	ctx.insert("page_url", &format!("/mod/{}", modd.id));
+
+	let html = template
+		.lock()
+		.unwrap()
+		.render("mod_details.html", &ctx)
+		.map_err(|err| {
+			log::error!("{err}");
+			actix_web::error::ErrorInternalServerError("Template error")
+		})?;
+
+	Ok(Html::new(html))
+}
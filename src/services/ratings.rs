@@ -1,36 +1,54 @@
+use actix_multipart::form::{MultipartForm, bytes::Bytes};
 use actix_web::{
-	HttpRequest, HttpResponse, Responder, get, post,
-	web::{Data, Form, Html, ReqData},
+	HttpRequest, HttpResponse, Responder, get,
+	http::header::{ContentDisposition, DispositionParam, DispositionType},
+	post,
+	web::{Data, Form, Html, Query, ReqData},
 };
 use serde::Deserialize;
-use std::sync::Mutex;
+use std::{collections::HashSet, error::Error, sync::Mutex};
 use tera::{Context, Tera};
 use uuid::Uuid;
 
 use crate::{
 	db::{Database, ModQueryOptions},
+	env::Env,
 	middlewares::TokenValidator,
-	mods::Rating,
+	mods::{Mod, Rating},
+	profile::Profile,
 	services::{
-		header_redirect_to,
-		settings::{SETTINGS_COOKIE, Settings},
+		format_count, format_day, format_size, header_redirect_to,
+		icons::icon_url,
+		settings::{SETTINGS_COOKIE, Settings, load_settings},
 	},
 };
 
 use super::users::TokenClaims;
 
+/// most recently rated mods shown on the liked and disliked mods pages, the rest are in the
+/// rating history
+const RATED_MODS_LIMIT: i16 = 100;
+
+#[derive(Deserialize)]
+struct RatingPageParams {
+	/// mod to show instead of the next one in the queue, e.g. after undoing its rating
+	mod_id: Option<Uuid>,
+}
+
 #[get("/rate", wrap = "TokenValidator")]
 async fn rating_page(
 	template: Data<Mutex<Tera>>,
 	db: Data<Database>,
+	env: Data<Env>,
 	req_user: ReqData<TokenClaims>,
 	request: HttpRequest,
+	params: Query<RatingPageParams>,
 ) -> Result<Html, actix_web::Error> {
 	let mut ctx = Context::new();
 
 	let settings = request
 		.cookie(SETTINGS_COOKIE)
-		.map(|cookie| {
+		.and_then(|cookie| {
 			serde_json::from_str::<Settings>(cookie.value())
 				.inspect_err(|error| {
 					log::error!("Error deserializing settings cookie: {error}");
@@ -41,32 +59,68 @@ async fn rating_page(
 				})
 				.ok()
 		})
-		.flatten()
 		.unwrap_or_default();
 
+	let community = settings.selected_community(&env);
 	let options = ModQueryOptions {
+		community: community.to_string(),
 		limit: 1,
-		ignored_categories: settings.excluded_category,
+		ignored_categories: settings.excluded_categories(community),
 		include_deprecated: settings.include_deprecated,
 		include_nsfw: settings.include_nsfw,
+		order: settings.queue_order,
 	};
 
-	let mods = db
-		.get_mods(&options, req_user.id)
+	let requested = match params.mod_id {
+		Some(mod_id) => requested_mod(&db, &mod_id, community, req_user.id)
+			.await
+			.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?,
+		None => None,
+	};
+
+	let modd = match requested {
+		Some(modd) => modd,
+		None => db
+			.get_mods(&options, req_user.id)
+			.await
+			.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?
+			.into_iter()
+			.next()
+			.ok_or_else(|| actix_web::error::ErrorInternalServerError("No mods found"))?,
+	};
+
+	let versions = db
+		.get_mod_versions(&modd.id)
 		.await
 		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
 
-	let modd = mods
-		.first()
-		.ok_or_else(|| actix_web::error::ErrorInternalServerError("No mods found"))?;
+	let dependencies = db
+		.get_dependencies(&modd.id, community)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?
+		.into_iter()
+		.map(|dependency| dependency.full_name)
+		.collect::<Vec<_>>();
 
 	ctx.insert("name", &modd.name);
 	ctx.insert("owner", &modd.owner);
-	ctx.insert("icon_url", &modd.icon_url);
+	ctx.insert("icon_url", &icon_url(&modd));
 	ctx.insert("description", &modd.description);
 	ctx.insert("package_url", &modd.package_url);
 	ctx.insert("mod_id", &modd.id.to_string());
 	ctx.insert("categories", &modd.categories.join(", "));
+	ctx.insert("dependencies", &dependencies.join(", "));
+	ctx.insert("downloads", &format_count(modd.downloads));
+	ctx.insert("file_size", &format_size(modd.file_size.max(0) as u64));
+	ctx.insert("date_created", &format_day(modd.date_created));
+	ctx.insert("updated_date", &format_day(modd.updated_date));
+	ctx.insert("pinned", &modd.pinned);
+	ctx.insert("website_url", &modd.website_url);
+	ctx.insert("donation_link", &modd.donation_link);
+
+	if let Some(current_version) = versions.first() {
+		ctx.insert("version", &current_version.version_number);
+	}
 
 	let html = template
 		.lock()
@@ -77,12 +131,45 @@ async fn rating_page(
 	Ok(Html::new(html))
 }
 
+/// The mod if it is a listed mod of the selected community the user hasn't rated, otherwise
+/// the rating page falls back to the queue
+async fn requested_mod(
+	db: &Database,
+	mod_id: &Uuid,
+	community: &str,
+	user_id: i32,
+) -> Result<Option<Mod>, Box<dyn Error>> {
+	if db.get_mod_rating(mod_id, user_id).await?.is_some() {
+		return Ok(None);
+	}
+
+	let modd = db.get_mod(mod_id, community).await?;
+	Ok(modd.filter(|modd| !modd.delisted))
+}
+
 #[derive(Deserialize)]
 struct RatingForm {
 	mod_id: String,
 	rating: Rating,
+	/// page to go back to after rating, the rating page if not given
+	return_to: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ClearRatingForm {
+	mod_id: String,
+	return_to: Option<String>,
 }
 
+/// `return_to` if it's a page of this site, so the forms can't be used to redirect elsewhere
+fn return_path(return_to: Option<&str>) -> &str {
+	return_to
+		.filter(|path| path.starts_with('/') && !path.starts_with("//") && !path.starts_with("/\\"))
+		.unwrap_or("/rate")
+}
+
+/// Rate a mod, rating a mod again keeps the first rating so a form submitted twice does no
+/// harm. Existing ratings are changed with `change_rating`.
 #[post("/rate", wrap = "TokenValidator")]
 async fn post_rating(
 	params: Form<RatingForm>,
@@ -93,10 +180,80 @@ async fn post_rating(
 
 	let uuid = Uuid::parse_str(&params.mod_id)
 		.map_err(|_| actix_web::error::ErrorBadRequest("Bad mod uuid"))?;
-	db.insert_mod_rating(&uuid, &params.rating, user_id).await?;
+	db.insert_mod_rating(&uuid, &params.rating, user_id)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
 
 	Ok(HttpResponse::Created()
-		.insert_header(header_redirect_to("/rate"))
+		.insert_header(header_redirect_to(return_path(params.return_to.as_deref())))
+		.finish())
+}
+
+/// change an existing rating, e.g. from the page of the mod
+#[post("/rate/change", wrap = "TokenValidator")]
+async fn change_rating(
+	params: Form<RatingForm>,
+	db: Data<Database>,
+	req_user: ReqData<TokenClaims>,
+) -> Result<impl Responder, actix_web::Error> {
+	let uuid = Uuid::parse_str(&params.mod_id)
+		.map_err(|_| actix_web::error::ErrorBadRequest("Bad mod uuid"))?;
+
+	let changed = db
+		.update_mod_rating(&uuid, &params.rating, req_user.id)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+	if !changed {
+		return Err(actix_web::error::ErrorNotFound("Mod not rated"));
+	}
+
+	Ok(HttpResponse::Ok()
+		.insert_header(header_redirect_to(return_path(params.return_to.as_deref())))
+		.finish())
+}
+
+/// Remove a rating, the mod comes up again on the rating page. Clearing a rating that doesn't
+/// exist does nothing.
+#[post("/rate/clear", wrap = "TokenValidator")]
+async fn clear_rating(
+	params: Form<ClearRatingForm>,
+	db: Data<Database>,
+	req_user: ReqData<TokenClaims>,
+) -> Result<impl Responder, actix_web::Error> {
+	let uuid = Uuid::parse_str(&params.mod_id)
+		.map_err(|_| actix_web::error::ErrorBadRequest("Bad mod uuid"))?;
+
+	db.delete_mod_rating(&uuid, req_user.id)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+
+	Ok(HttpResponse::Ok()
+		.insert_header(header_redirect_to(return_path(params.return_to.as_deref())))
+		.finish())
+}
+
+/// Remove the most recent rating and show the mod again, for when a mod was rated by mistake
+#[post("/rate/undo", wrap = "TokenValidator")]
+async fn undo_rating(
+	db: Data<Database>,
+	env: Data<Env>,
+	req_user: ReqData<TokenClaims>,
+	request: HttpRequest,
+) -> Result<impl Responder, actix_web::Error> {
+	let settings = load_settings(&request);
+
+	let undone = db
+		.undo_last_rating(settings.selected_community(&env), req_user.id)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+
+	let redirect = match undone {
+		Some(mod_id) => format!("/rate?mod_id={mod_id}"),
+		None => "/rate".to_string(),
+	};
+
+	Ok(HttpResponse::Ok()
+		.insert_header(header_redirect_to(&redirect))
 		.finish())
 }
 
@@ -104,17 +261,76 @@ async fn post_rating(
 async fn rated_mods(
 	template: Data<Mutex<Tera>>,
 	db: Data<Database>,
+	env: Data<Env>,
 	req_user: ReqData<TokenClaims>,
+	request: HttpRequest,
 ) -> Result<Html, actix_web::Error> {
-	let user_id = req_user.id;
+	render_rated_mods_page(&template, &db, &env, req_user.id, &request, Rating::Like).await
+}
+
+#[get("/dislikes", wrap = "TokenValidator")]
+async fn disliked_mods(
+	template: Data<Mutex<Tera>>,
+	db: Data<Database>,
+	env: Data<Env>,
+	req_user: ReqData<TokenClaims>,
+	request: HttpRequest,
+) -> Result<Html, actix_web::Error> {
+	render_rated_mods_page(&template, &db, &env, req_user.id, &request, Rating::Dislike).await
+}
 
-	let mods = db
-		.get_rated_mods(&Rating::Like, 100, user_id)
+async fn render_rated_mods_page(
+	template: &Mutex<Tera>,
+	db: &Database,
+	env: &Env,
+	user_id: i32,
+	request: &HttpRequest,
+	rating: Rating,
+) -> Result<Html, actix_web::Error> {
+	let settings = load_settings(request);
+	let community = settings.selected_community(env);
+
+	// one more than is shown, to know if the list is cut off
+	let mut mods = db
+		.get_rated_mods(&rating, community, RATED_MODS_LIMIT + 1, user_id)
 		.await
 		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+	let cut_off = mods.len() > RATED_MODS_LIMIT as usize;
+	mods.truncate(RATED_MODS_LIMIT as usize);
 
 	let mut ctx = Context::new();
+
+	// only liked mods get installed, so only their dependencies matter. The dependencies are
+	// those of all liked mods, not only the ones shown.
+	if rating == Rating::Like {
+		let liked_ids = db
+			.get_rated_mod_ids(&rating, community, user_id)
+			.await
+			.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+		let dependencies = db
+			.get_dependencies_of_mods(&liked_ids, community)
+			.await
+			.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?
+			.into_iter()
+			.filter(|dependency| {
+				dependency
+					.mod_id
+					.is_none_or(|mod_id| !liked_ids.contains(&mod_id))
+			})
+			.collect::<Vec<_>>();
+		ctx.insert("dependencies", &dependencies);
+	}
+
+	let (page_url, other_rating) = match rating {
+		Rating::Like => ("/likes", Rating::Dislike),
+		Rating::Dislike => ("/dislikes", Rating::Like),
+	};
+
 	ctx.insert("mods", &mods);
+	ctx.insert("cut_off", &cut_off);
+	ctx.insert("rating", &rating);
+	ctx.insert("other_rating", &other_rating);
+	ctx.insert("page_url", page_url);
 
 	let html = template
 		.lock()
@@ -124,3 +340,140 @@ async fn rated_mods(
 
 	Ok(Html::new(html))
 }
+
+#[get("/likes/export", wrap = "TokenValidator")]
+async fn export_liked_mods(
+	db: Data<Database>,
+	env: Data<Env>,
+	req_user: ReqData<TokenClaims>,
+	request: HttpRequest,
+) -> Result<impl Responder, actix_web::Error> {
+	let user_id = req_user.id;
+	let settings = load_settings(&request);
+	let community = settings.selected_community(&env);
+
+	let mut mod_ids = db
+		.get_rated_mod_ids(&Rating::Like, community, user_id)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+
+	let dependencies = db
+		.get_dependencies_of_mods(&mod_ids, community)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+	mod_ids.extend(dependencies.into_iter().filter_map(|d| d.mod_id));
+
+	let packages = db
+		.get_latest_versions(&mod_ids)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+
+	let r2z = Profile::new("liked-mods", &packages)
+		.to_r2z()
+		.map_err(|err| {
+			log::error!("Error creating profile export: {err}");
+			actix_web::error::ErrorInternalServerError("Error creating profile")
+		})?;
+
+	let content_disposition = ContentDisposition {
+		disposition: DispositionType::Attachment,
+		parameters: vec![DispositionParam::Filename("export.r2z".to_string())],
+	};
+
+	Ok(HttpResponse::Ok()
+		.content_type("application/zip")
+		.insert_header(content_disposition)
+		.body(r2z))
+}
+
+#[get("/import-profile", wrap = "TokenValidator")]
+async fn import_profile_page(template: Data<Mutex<Tera>>) -> Result<Html, actix_web::Error> {
+	render_import_profile_page(&template, &Context::new())
+}
+
+#[derive(MultipartForm)]
+struct ProfileUpload {
+	profile: Bytes,
+}
+
+#[post("/import-profile", wrap = "TokenValidator")]
+async fn import_profile(
+	template: Data<Mutex<Tera>>,
+	db: Data<Database>,
+	env: Data<Env>,
+	req_user: ReqData<TokenClaims>,
+	request: HttpRequest,
+	MultipartForm(upload): MultipartForm<ProfileUpload>,
+) -> Result<Html, actix_web::Error> {
+	let user_id = req_user.id;
+	let settings = load_settings(&request);
+	let mut ctx = Context::new();
+
+	let profile = match Profile::parse(&upload.profile.data) {
+		Ok(profile) => profile,
+		Err(err) => {
+			log::warn!("Failed to parse uploaded profile: {err}");
+			ctx.insert(
+				"error",
+				"Can't read the profile, please upload an export.r2z or export.r2x file exported from r2modman.",
+			);
+			return render_import_profile_page(&template, &ctx);
+		}
+	};
+
+	let full_names = profile
+		.mods
+		.into_iter()
+		.map(|m| m.name)
+		.collect::<HashSet<_>>()
+		.into_iter()
+		.collect::<Vec<_>>();
+
+	let matches = db
+		.find_mods_by_full_name(&full_names, settings.selected_community(&env), user_id)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+
+	let (already_rated, unrated): (Vec<_>, Vec<_>) =
+		matches.iter().partition(|modd| modd.rating.is_some());
+
+	let unrated_ids = unrated.iter().map(|modd| modd.id).collect::<Vec<_>>();
+	db.insert_mod_ratings(&unrated_ids, &Rating::Like, user_id)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+
+	let liked = unrated
+		.iter()
+		.map(|modd| &modd.full_name)
+		.collect::<Vec<_>>();
+	let already_rated = already_rated
+		.iter()
+		.map(|modd| &modd.full_name)
+		.collect::<Vec<_>>();
+
+	let mut unmatched = full_names
+		.iter()
+		.filter(|name| !matches.iter().any(|m| &m.full_name == *name))
+		.collect::<Vec<_>>();
+	unmatched.sort();
+
+	ctx.insert("imported", &true);
+	ctx.insert("liked", &liked);
+	ctx.insert("already_rated", &already_rated);
+	ctx.insert("unmatched", &unmatched);
+
+	render_import_profile_page(&template, &ctx)
+}
+
+fn render_import_profile_page(
+	template: &Mutex<Tera>,
+	ctx: &Context,
+) -> Result<Html, actix_web::Error> {
+	let html = template
+		.lock()
+		.unwrap()
+		.render("import_profile.html", ctx)
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Template error"))?;
+
+	Ok(Html::new(html))
+}
@@ -0,0 +1,168 @@
+use std::{collections::BTreeSet, sync::Mutex};
+
+use actix_web::{
+	HttpRequest, get,
+	web::{Data, Html, ReqData},
+};
+use serde::{Deserialize, Serialize};
+use serde_qs::actix::QsQuery;
+use tera::{Context, Tera};
+
+use crate::{
+	db::{Database, ModSearchOptions},
+	env::Env,
+	middlewares::TokenValidator,
+	mods::{Rating, SearchResult},
+	services::{
+		format_count,
+		icons::icon_url,
+		settings::{CategoryCheckbox, load_settings},
+	},
+};
+
+use super::users::TokenClaims;
+
+const RESULTS_PER_PAGE: i64 = 20;
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct SearchParams {
+	#[serde(default)]
+	q: String,
+	/// sorted so the page links stay the same
+	#[serde(default)]
+	category: BTreeSet<String>,
+	#[serde(default = "first_page")]
+	page: i64,
+}
+
+fn first_page() -> i64 {
+	1
+}
+
+impl SearchParams {
+	fn url(&self, page: i64) -> String {
+		let params = SearchParams {
+			page,
+			..self.clone()
+		};
+
+		match serde_qs::to_string(&params) {
+			Ok(query) => format!("/search?{query}"),
+			Err(err) => {
+				log::error!("Error serializing search parameters: {err}");
+				"/search".to_string()
+			}
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+struct SearchItem {
+	id: String,
+	name: String,
+	owner: String,
+	description: String,
+	package_url: String,
+	icon_url: String,
+	categories: String,
+	downloads: String,
+	rating: Option<Rating>,
+}
+
+impl From<SearchResult> for SearchItem {
+	fn from(result: SearchResult) -> Self {
+		let modd = result.modd;
+		Self {
+			id: modd.id.to_string(),
+			icon_url: icon_url(&modd),
+			categories: modd.categories.join(", "),
+			downloads: format_count(modd.downloads),
+			name: modd.name,
+			owner: modd.owner,
+			description: modd.description,
+			package_url: modd.package_url,
+			rating: result.rating,
+		}
+	}
+}
+
+/// Full-text search of the mods of the selected community. Searching without terms lists the
+/// mods of the chosen categories.
+#[get("/search", wrap = "TokenValidator")]
+async fn search_page(
+	template: Data<Mutex<Tera>>,
+	db: Data<Database>,
+	env: Data<Env>,
+	req_user: ReqData<TokenClaims>,
+	request: HttpRequest,
+	params: QsQuery<SearchParams>,
+) -> Result<Html, actix_web::Error> {
+	let params = params.into_inner();
+	let settings = load_settings(&request);
+	let community = settings.selected_community(&env);
+	let page = params.page.max(1);
+
+	let categories = db
+		.get_categories(community)
+		.await
+		.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?
+		.into_iter()
+		.map(|c| {
+			let checked = params.category.contains(&c.name);
+			CategoryCheckbox::new(c, checked)
+		})
+		.collect::<Vec<_>>();
+
+	let mut ctx = Context::new();
+	ctx.insert("query", &params.q);
+	ctx.insert("categories", &categories);
+
+	let searched = !params.q.trim().is_empty() || !params.category.is_empty();
+	if searched {
+		let options = ModSearchOptions {
+			community: community.to_string(),
+			query: params.q.clone(),
+			categories: params.category.iter().cloned().collect(),
+			include_deprecated: settings.include_deprecated,
+			include_nsfw: settings.include_nsfw,
+			limit: RESULTS_PER_PAGE,
+			offset: (page - 1).saturating_mul(RESULTS_PER_PAGE),
+		};
+
+		let search = db
+			.search_mods(&options, req_user.id)
+			.await
+			.map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+
+		let pages = (search.total + RESULTS_PER_PAGE - 1) / RESULTS_PER_PAGE;
+		let results = search
+			.items
+			.into_iter()
+			.map(SearchItem::from)
+			.collect::<Vec<_>>();
+
+		ctx.insert("results", &results);
+		ctx.insert("total", &search.total);
+		ctx.insert("page", &page);
+		ctx.insert("pages", &pages);
+		ctx.insert("current_url", &params.url(page));
+		if page > 1 {
+			ctx.insert("previous_url", &params.url(page - 1));
+		}
+		if page < pages {
+			ctx.insert("next_url", &params.url(page + 1));
+		}
+	}
+	ctx.insert("searched", &searched);
+
+	let html = template
+		.lock()
+		.unwrap()
+		.render("search.html", &ctx)
+		.map_err(|err| {
+			log::error!("{err}");
+			actix_web::error::ErrorInternalServerError("Template error")
+		})?;
+
+	Ok(Html::new(html))
+}
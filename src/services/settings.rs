@@ -1,4 +1,7 @@
-use std::{collections::HashSet, sync::Mutex};
+use std::{
+	collections::{HashMap, HashSet},
+	sync::Mutex,
+};
 
 use actix_web::{
 	HttpRequest, HttpResponse, Responder,
@@ -11,30 +14,108 @@ use serde_qs::actix::QsForm;
 use tera::{Context, Tera};
 
 use crate::{
-	db::Database, middlewares::TokenValidator, mods::Category, services::header_redirect_to,
+	db::{Database, QueueOrder},
+	env::Env,
+	middlewares::TokenValidator,
+	mods::{Category, DEFAULT_COMMUNITY},
+	services::header_redirect_to,
 };
 
-pub const SETTINGS_COOKIE: &'static str = "lcmt-settings";
+pub const SETTINGS_COOKIE: &str = "lcmt-settings";
 
 #[derive(Debug, Deserialize, Serialize, Default)]
 pub struct Settings {
+	/// excluded categories of each community, see `excluded_categories`
 	#[serde(default)]
-	pub excluded_category: HashSet<String>,
+	excluded_categories: HashMap<String, HashSet<String>>,
+	/// excluded categories saved before they were stored per community, they are of the default
+	/// community
+	#[serde(default, skip_serializing)]
+	excluded_category: HashSet<String>,
 	#[serde(default)]
 	pub include_nsfw: bool,
 	#[serde(default)]
 	pub include_deprecated: bool,
+	#[serde(default)]
+	pub queue_order: QueueOrder,
+	/// Thunderstore community the user is rating mods of, see `selected_community`
+	#[serde(default)]
+	pub community: Option<String>,
+}
+
+impl Settings {
+	/// Selected community, falls back to the default one if nothing is selected or the selected
+	/// community is no longer imported
+	pub fn selected_community<'a>(&'a self, env: &'a Env) -> &'a str {
+		self.community
+			.as_deref()
+			.filter(|community| env.communities.iter().any(|c| c == community))
+			.unwrap_or(&env.communities[0])
+	}
+
+	/// categories of the community the user doesn't want to rate
+	pub fn excluded_categories(&self, community: &str) -> HashSet<String> {
+		match self.excluded_categories.get(community) {
+			Some(categories) => categories.clone(),
+			None if community == DEFAULT_COMMUNITY => self.excluded_category.clone(),
+			None => HashSet::new(),
+		}
+	}
+
+	/// excluded categories of all communities, including ones saved before they were stored per
+	/// community
+	fn into_excluded_categories(self) -> HashMap<String, HashSet<String>> {
+		let mut excluded_categories = self.excluded_categories;
+		if !self.excluded_category.is_empty() {
+			excluded_categories
+				.entry(DEFAULT_COMMUNITY.to_string())
+				.or_insert(self.excluded_category);
+		}
+		excluded_categories
+	}
+}
+
+/// The settings form. Only the excluded categories of one community are shown at a time.
+#[derive(Debug, Deserialize)]
+struct SettingsForm {
+	#[serde(default)]
+	excluded_category: HashSet<String>,
+	/// community of the shown categories, which differs from `community` if another community
+	/// was selected in the same form
+	categories_community: String,
+	#[serde(default)]
+	include_nsfw: bool,
+	#[serde(default)]
+	include_deprecated: bool,
+	#[serde(default)]
+	queue_order: QueueOrder,
+	#[serde(default)]
+	community: Option<String>,
+}
+
+/// settings of the user, or the default settings if the cookie is missing or unreadable
+pub fn load_settings(request: &HttpRequest) -> Settings {
+	request
+		.cookie(SETTINGS_COOKIE)
+		.and_then(|cookie| serde_json::from_str::<Settings>(cookie.value()).ok())
+		.unwrap_or_default()
 }
 
 #[derive(Debug, Serialize)]
-struct CategoryCheckbox {
+struct CommunityOption<'a> {
+	slug: &'a str,
+	selected: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryCheckbox {
 	name: String,
 	id: i32,
 	checked: bool,
 }
 
 impl CategoryCheckbox {
-	fn new(category: Category, checked: bool) -> Self {
+	pub fn new(category: Category, checked: bool) -> Self {
 		Self {
 			name: category.name,
 			id: category.id,
@@ -47,29 +128,40 @@ impl CategoryCheckbox {
 pub async fn settings_page(
 	template: Data<Mutex<Tera>>,
 	db: Data<Database>,
+	env: Data<Env>,
 	request: HttpRequest,
 ) -> Result<impl Responder, actix_web::Error> {
-	let settings = request
-		.cookie(SETTINGS_COOKIE)
-		.map(|cookie| serde_json::from_str::<Settings>(cookie.value()).ok())
-		.flatten()
-		.unwrap_or_default();
+	let settings = load_settings(&request);
+	let community = settings.selected_community(&env);
 
 	let mut ctx = Context::new();
 
+	let communities = env
+		.communities
+		.iter()
+		.map(|slug| CommunityOption {
+			slug,
+			selected: slug == community,
+		})
+		.collect::<Vec<_>>();
+
+	let excluded_categories = settings.excluded_categories(community);
 	let categories = db
-		.get_categories()
+		.get_categories(community)
 		.await?
 		.into_iter()
 		.map(|c| {
-			let checked = settings.excluded_category.contains(&c.name);
+			let checked = excluded_categories.contains(&c.name);
 			CategoryCheckbox::new(c, checked)
 		})
 		.collect::<Vec<_>>();
 
+	ctx.insert("communities", &communities);
+	ctx.insert("community", community);
 	ctx.insert("categories", &categories);
 	ctx.insert("nsfw_checked", &settings.include_nsfw);
 	ctx.insert("deprecated_checked", &settings.include_deprecated);
+	ctx.insert("queue_order", &settings.queue_order);
 
 	let html = template
 		.lock()
@@ -81,8 +173,29 @@ pub async fn settings_page(
 }
 
 #[post("/save-settings", wrap = "TokenValidator")]
-pub async fn save_settings(settings: QsForm<Settings>) -> Result<impl Responder, actix_web::Error> {
-	let settings_json = serde_json::to_string(&settings.into_inner())
+pub async fn save_settings(
+	form: QsForm<SettingsForm>,
+	env: Data<Env>,
+	request: HttpRequest,
+) -> Result<impl Responder, actix_web::Error> {
+	let form = form.into_inner();
+
+	// keep the excluded categories of the other communities
+	let mut excluded_categories = load_settings(&request).into_excluded_categories();
+	if env.communities.contains(&form.categories_community) {
+		excluded_categories.insert(form.categories_community, form.excluded_category);
+	}
+
+	let settings = Settings {
+		excluded_categories,
+		excluded_category: HashSet::new(),
+		include_nsfw: form.include_nsfw,
+		include_deprecated: form.include_deprecated,
+		queue_order: form.queue_order,
+		community: form.community,
+	};
+
+	let settings_json = serde_json::to_string(&settings)
 		.map_err(|_| actix_web::error::ErrorInternalServerError("Unknown error"))?;
 
 	let cookie = Cookie::build(SETTINGS_COOKIE, settings_json)
@@ -73,12 +73,12 @@ async fn create_user(
 				.insert_header(header_redirect_to("/"))
 				.finish();
 
-			return Ok(Either::Left(response));
+			Ok(Either::Left(response))
 		}
 		Ok(None) => {
 			let response =
 				get_create_user_page(template, Some("That username is already taken")).await?;
-			return Ok(Either::Right(response));
+			Ok(Either::Right(response))
 		}
 		Err(_) => Err(actix_web::error::ErrorInternalServerError("Database error")),
 	}